@@ -0,0 +1,97 @@
+use std::io;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use embedded_graphics::primitives::{Circle, PointsIter};
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+use terminal_display::{Color, TerminalDisplay};
+
+/// A color which is deliberately expensive to compute, like the HSV gradient in
+/// the `color_wave` example.
+fn expensive_color(point: Point) -> Color {
+    let t = (point.x as f64).sin() * (point.y as f64).cos();
+    let channel = |offset: f64| ((t + offset).sin() * 127.5 + 127.5) as u8;
+    Color::Rgb(Rgb888::new(channel(0.0), channel(2.0), channel(4.0)))
+}
+
+/// Fill an area which hangs off the bottom-right of the display, so that a
+/// lot of the colors aren't needed.
+fn fill_overhanging(c: &mut Criterion) {
+    let mut display = TerminalDisplay::with_writer(io::sink()).unwrap();
+    let size = display.size();
+    let area = Rectangle::new(
+        Point::new(size.width as i32 / 2, size.height as i32 / 2),
+        size,
+    );
+    c.bench_function("fill overhanging", |b| {
+        b.iter(|| {
+            display
+                .fill_contiguous(&area, area.points().map(expensive_color))
+                .unwrap();
+        })
+    });
+}
+
+/// Fill an area which exactly covers the display.
+fn fill_exact(c: &mut Criterion) {
+    let mut display = TerminalDisplay::with_writer(io::sink()).unwrap();
+    let area = display.bounding_box();
+    c.bench_function("fill exact", |b| {
+        b.iter(|| {
+            display
+                .fill_contiguous(&area, area.points().map(expensive_color))
+                .unwrap();
+        })
+    });
+}
+
+/// Draw a filled circle covering most of the display one pixel at a time and
+/// flush it, alternating between two colors so that it has to be redrawn each
+/// time.
+fn draw_circle(c: &mut Criterion) {
+    let mut display = TerminalDisplay::with_writer(io::sink()).unwrap();
+    let size = display.size();
+    let circle = Circle::with_center(
+        Point::new(size.width as i32 / 2, size.height as i32 / 2),
+        size.width.min(size.height),
+    );
+    let mut colors = [Color::Red, Color::Blue].into_iter().cycle();
+    c.bench_function("draw circle", |b| {
+        b.iter(|| {
+            let color = colors.next().unwrap();
+            display
+                .draw_iter(circle.points().map(|point| Pixel(point, color)))
+                .unwrap();
+            display.flush().unwrap();
+        })
+    });
+}
+
+/// Fill the circle's bounding box all at once and flush it, to compare against
+/// drawing the circle pixel by pixel.
+fn fill_circle_bounds(c: &mut Criterion) {
+    let mut display = TerminalDisplay::with_writer(io::sink()).unwrap();
+    let size = display.size();
+    let area = Circle::with_center(
+        Point::new(size.width as i32 / 2, size.height as i32 / 2),
+        size.width.min(size.height),
+    )
+    .bounding_box();
+    let mut colors = [Color::Red, Color::Blue].into_iter().cycle();
+    c.bench_function("fill circle bounds", |b| {
+        b.iter(|| {
+            display.fill_solid(&area, colors.next().unwrap()).unwrap();
+            display.flush().unwrap();
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    fill_overhanging,
+    fill_exact,
+    draw_circle,
+    fill_circle_bounds
+);
+criterion_main!(benches);
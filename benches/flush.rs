@@ -0,0 +1,49 @@
+use std::io::{self, Write};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use crossterm::style::{self, Colors};
+use crossterm::{cursor, QueueableCommand};
+use embedded_graphics_core::prelude::*;
+use terminal_display::{Color, TerminalDisplay};
+
+/// Fill the whole display with a solid color and flush it, alternating between
+/// two colors so that every cell has to be redrawn each time; compared against
+/// writing each cell on its own, with a cursor move and colors of its own,
+/// which is what flushing would do without batching cells into runs.
+fn fill_and_flush(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fill and flush");
+
+    let mut display = TerminalDisplay::with_writer(io::sink()).unwrap();
+    let size = display.size();
+    let mut colors = [Color::Red, Color::Blue].into_iter().cycle();
+    group.bench_function("batched", |b| {
+        b.iter(|| {
+            display.clear(colors.next().unwrap()).unwrap();
+            display.flush().unwrap();
+        })
+    });
+
+    // Half-block cells are two pixels tall.
+    let (columns, rows) = (size.width as u16, (size.height / 2) as u16);
+    let mut out = io::sink();
+    let mut colors = [style::Color::Red, style::Color::Blue].into_iter().cycle();
+    group.bench_function("per cell", |b| {
+        b.iter(|| {
+            let color = colors.next().unwrap();
+            for row in 0..rows {
+                for column in 0..columns {
+                    out.queue(cursor::MoveTo(column, row)).unwrap();
+                    out.queue(style::SetColors(Colors::new(color, color)))
+                        .unwrap();
+                    out.queue(style::Print('▀')).unwrap();
+                }
+            }
+            out.flush().unwrap();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, fill_and_flush);
+criterion_main!(benches);
@@ -1,21 +1,21 @@
-use std::io;
-use std::time::Instant;
+use std::io::{self, Write};
+use std::time::Duration;
 
-use crossterm::{cursor, ExecutableCommand};
 use embedded_graphics_core::pixelcolor::Rgb888;
 use embedded_graphics_core::prelude::*;
 use palette::{Hsv, IntoColor, IntoComponent, Srgb};
-use terminal_display::{Color, TerminalDisplay};
+use terminal_display::{Animation, Color, TerminalDisplay, TerminalError};
 
-fn main() -> io::Result<()> {
-    let mut display = TerminalDisplay::new()?;
-
-    io::stdout().execute(cursor::Hide)?;
+/// A rainbow moving diagonally across the display.
+struct ColorWave;
 
-    let start = Instant::now();
-    loop {
+impl Animation for ColorWave {
+    fn render<W: Write>(
+        &mut self,
+        display: &mut TerminalDisplay<W>,
+        elapsed: Duration,
+    ) -> Result<(), TerminalError> {
         let bounding_box = display.bounding_box();
-        let elapsed = start.elapsed();
         display.fill_contiguous(
             &bounding_box,
             bounding_box.points().map(|point| {
@@ -27,7 +27,17 @@ fn main() -> io::Result<()> {
                     color.blue.into_component(),
                 ))
             }),
-        )?;
-        display.flush()?;
+        )
     }
 }
+
+fn main() -> io::Result<()> {
+    // Raw mode lets a key press stop the animation straight away.
+    let mut display = TerminalDisplay::builder()
+        .raw_mode(true)
+        .hide_cursor(true)
+        .build()?;
+
+    terminal_display::run(&mut display, &mut ColorWave, 60)?;
+    Ok(())
+}
@@ -2,6 +2,7 @@
 //! `draw_contiguous`).
 
 use std::io;
+use std::thread;
 
 use embedded_graphics_core::prelude::*;
 use terminal_display::{Color, TerminalDisplay};
@@ -14,6 +15,8 @@ fn main() -> io::Result<()> {
     display.clear(Color::BgColor)?;
     display.draw_iter((0..len as i32).map(|i| Pixel(Point::new(i, i), Color::FgColor)))?;
 
-    display.flush()?;
-    loop {}
+    display.present()?;
+    loop {
+        thread::park();
+    }
 }
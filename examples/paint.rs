@@ -0,0 +1,40 @@
+use std::io;
+use std::time::Duration;
+
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEventKind,
+};
+use crossterm::execute;
+use terminal_display::{Color, TerminalDisplay};
+
+fn main() -> io::Result<()> {
+    let mut display = TerminalDisplay::builder()
+        .alternate_screen(true)
+        .raw_mode(true)
+        .hide_cursor(true)
+        .build()?;
+    execute!(io::stdout(), EnableMouseCapture)?;
+
+    loop {
+        match display.poll_event(Duration::from_millis(100))? {
+            Some(Event::Key(key)) if key.code == KeyCode::Char('q') => break,
+            Some(Event::Mouse(mouse)) => {
+                let color = match mouse.kind {
+                    MouseEventKind::Down(MouseButton::Left)
+                    | MouseEventKind::Drag(MouseButton::Left) => Color::White,
+                    MouseEventKind::Down(MouseButton::Right)
+                    | MouseEventKind::Drag(MouseButton::Right) => Color::BgColor,
+                    _ => continue,
+                };
+                if let Some(point) = display.mouse_to_point(mouse.column, mouse.row, true) {
+                    display.set_pixel(point, color)?;
+                    display.present()?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    execute!(io::stdout(), DisableMouseCapture)?;
+    Ok(())
+}
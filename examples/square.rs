@@ -2,6 +2,7 @@
 //! boundaries to make sure our edge cases work properly.
 
 use std::io;
+use std::thread;
 
 use embedded_graphics::prelude::*;
 use embedded_graphics::primitives::{PrimitiveStyleBuilder, Rectangle};
@@ -23,7 +24,9 @@ fn main() -> io::Result<()> {
     .into_styled(style)
     .draw(&mut display)?;
 
-    display.flush()?;
+    display.present()?;
 
-    loop {}
+    loop {
+        thread::park();
+    }
 }
@@ -0,0 +1,73 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crossterm::event::Event;
+
+use crate::{TerminalDisplay, TerminalError};
+
+/// Something which draws a new frame every time it's asked to, to be shown
+/// by [`run`].
+///
+/// [`run`]: crate::run
+pub trait Animation {
+    /// Draw the frame which should be shown `elapsed` after the animation
+    /// started.
+    ///
+    /// This only needs to draw what's changed since the last frame: whatever
+    /// is already on the display stays there, and the frame gets presented
+    /// afterwards. The size of the display can change in between frames if
+    /// the terminal's resized.
+    fn render<W: Write>(
+        &mut self,
+        display: &mut TerminalDisplay<W>,
+        elapsed: Duration,
+    ) -> Result<(), TerminalError>;
+}
+
+/// Show `animation` on `display` at up to `fps` frames per second, until a
+/// key is pressed.
+///
+/// Each frame is drawn with [`Animation::render`], and then presented with
+/// [`TerminalDisplay::present_at_most`], so that the loop sleeps in between
+/// frames rather than using more CPU than it needs to; which also means that
+/// [`TerminalDisplay::set_adaptive_frame_rate`] can be used to skip frames
+/// the terminal can't keep up with. Resize events are passed on to the
+/// display with [`TerminalDisplay::notify_resize`].
+///
+/// Keys only get through as soon as they're pressed when the terminal is in
+/// raw mode, e.g. when the display was made with
+/// [`TerminalDisplay::fullscreen`]; otherwise, the terminal holds onto them
+/// until enter is pressed. If `fps` is 0, nothing gets drawn at all, and this
+/// just waits for a key.
+///
+/// [`TerminalDisplay::present_at_most`]: crate::TerminalDisplay::present_at_most
+/// [`TerminalDisplay::set_adaptive_frame_rate`]: crate::TerminalDisplay::set_adaptive_frame_rate
+/// [`TerminalDisplay::notify_resize`]: crate::TerminalDisplay::notify_resize
+/// [`TerminalDisplay::fullscreen`]: crate::TerminalDisplay::fullscreen
+pub fn run<W: Write>(
+    display: &mut TerminalDisplay<W>,
+    animation: &mut impl Animation,
+    fps: u32,
+) -> Result<(), TerminalError> {
+    let start = Instant::now();
+    loop {
+        // Deal with everything that's happened since the last frame, without waiting
+        // for anything else.
+        let timeout = match fps {
+            0 => Duration::MAX,
+            _ => Duration::ZERO,
+        };
+        while let Some(event) = display.poll_event(timeout)? {
+            match event {
+                Event::Key(_) => return Ok(()),
+                Event::Resize(..) => display.notify_resize(),
+                _ => {}
+            }
+        }
+
+        if fps != 0 {
+            animation.render(display, start.elapsed())?;
+            display.present_at_most(fps)?;
+        }
+    }
+}
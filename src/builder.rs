@@ -0,0 +1,352 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Stdout, Write};
+
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+use crate::render::RenderConfig;
+use crate::tty::SizeSource;
+use crate::{
+    Backend, Color, ColorMode, Dithering, GlyphSet, Orientation, RenderMode, Sampling,
+    TerminalDisplay, TerminalError,
+};
+
+/// A builder for configuring a [`TerminalDisplay`], created by
+/// [`TerminalDisplay::builder`].
+///
+/// Everything starts out the same as with [`TerminalDisplay::new`].
+///
+/// [`TerminalDisplay`]: crate::TerminalDisplay
+/// [`TerminalDisplay::builder`]: crate::TerminalDisplay::builder
+/// [`TerminalDisplay::new`]: crate::TerminalDisplay::new
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct TerminalDisplayBuilder {
+    config: RenderConfig,
+    dithering: Dithering,
+    sampling: Sampling,
+    fixed_size: Option<Size>,
+    region: Option<Rectangle>,
+    hide_cursor: bool,
+    alternate_screen: bool,
+    raw_mode: bool,
+    assume_narrow_blocks: bool,
+    keep_contents: bool,
+    strict: bool,
+    auto_flush: bool,
+    synchronized_output: bool,
+}
+
+impl Default for TerminalDisplayBuilder {
+    fn default() -> Self {
+        Self {
+            config: RenderConfig::default(),
+            dithering: Dithering::default(),
+            sampling: Sampling::default(),
+            fixed_size: None,
+            region: None,
+            hide_cursor: false,
+            alternate_screen: false,
+            raw_mode: false,
+            assume_narrow_blocks: true,
+            keep_contents: false,
+            strict: false,
+            auto_flush: false,
+            synchronized_output: false,
+        }
+    }
+}
+
+impl TerminalDisplayBuilder {
+    /// Set how the display gets drawn to the terminal.
+    ///
+    /// This changes the size of the display, so it can only be set here.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.config.backend = backend;
+        self
+    }
+
+    /// Set how cells get split up into pixels.
+    pub fn render_mode(mut self, render_mode: RenderMode) -> Self {
+        self.config.render_mode = render_mode;
+        self
+    }
+
+    /// Set which colors the terminal can display.
+    pub fn color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.config.color_mode = color_mode;
+        self
+    }
+
+    /// Set which characters are used to draw cells.
+    pub fn glyph_set(mut self, glyph_set: GlyphSet) -> Self {
+        self.config.glyph_set = glyph_set;
+        self
+    }
+
+    /// Set how the display is flipped or rotated when it's drawn.
+    ///
+    /// See [`TerminalDisplay::set_orientation`].
+    ///
+    /// [`TerminalDisplay::set_orientation`]: crate::TerminalDisplay::set_orientation
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.config.orientation = orientation;
+        self
+    }
+
+    /// Set the color to draw the parts of the display which are
+    /// [`Color::BgColor`] with, which is the terminal's default background
+    /// color unless set otherwise.
+    ///
+    /// This is useful for giving the display a background color of its own,
+    /// while still keeping track of which parts have been drawn over. It only
+    /// affects what gets displayed: [`TerminalDisplay::get_pixel`] still
+    /// returns [`Color::BgColor`] for them, and translucent colors still get
+    /// blended over black.
+    ///
+    /// [`Color::BgColor`]: crate::Color::BgColor
+    /// [`TerminalDisplay::get_pixel`]: crate::TerminalDisplay::get_pixel
+    pub fn canvas_color(mut self, color: Color) -> Self {
+        self.config.canvas_color = color;
+        self
+    }
+
+    /// Set whether to pick which dots are on in [`RenderMode::Braille`] by how
+    /// bright each pixel is.
+    ///
+    /// See [`TerminalDisplay::set_braille_threshold`] for details.
+    ///
+    /// [`RenderMode::Braille`]: crate::RenderMode::Braille
+    pub fn braille_threshold(mut self, threshold: Option<f32>) -> Self {
+        self.config.braille_threshold = threshold;
+        self
+    }
+
+    /// Draw cells which haven't had anything drawn in them, because all of
+    /// their pixels are [`Color::BgColor`], as `glyph` in `color` rather than
+    /// leaving them blank.
+    ///
+    /// This gives the empty parts of the display a pattern, like dots or a
+    /// faint grid. Anything drawn over a cell replaces the glyph, and it comes
+    /// back once the cell's cleared again. The glyph is drawn over the canvas
+    /// color, if one's been set with [`TerminalDisplayBuilder::canvas_color`],
+    /// and should only be a single column wide.
+    ///
+    /// Since the screen no longer looks the same as an empty display, every
+    /// cell is drawn the first time the display is presented. It isn't used
+    /// with [`Backend::Sixel`].
+    ///
+    /// [`Color::BgColor`]: crate::Color::BgColor
+    /// [`TerminalDisplayBuilder::canvas_color`]: crate::TerminalDisplayBuilder::canvas_color
+    /// [`Backend::Sixel`]: crate::Backend::Sixel
+    pub fn empty_cell(mut self, glyph: char, color: Color) -> Self {
+        self.config.empty_cell = Some((glyph, color));
+        self
+    }
+
+    /// Set how to dither colors which the terminal can't display.
+    ///
+    /// See [`TerminalDisplay::set_dithering`].
+    ///
+    /// [`TerminalDisplay::set_dithering`]: crate::TerminalDisplay::set_dithering
+    pub fn dithering(mut self, dithering: Dithering) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// Set how to pick the colors of pixels when scaling images.
+    ///
+    /// See [`TerminalDisplay::set_sampling`].
+    ///
+    /// [`TerminalDisplay::set_sampling`]: crate::TerminalDisplay::set_sampling
+    pub fn sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Make the display always `width` by `height` pixels, regardless of the
+    /// size of the terminal.
+    ///
+    /// See [`TerminalDisplay::with_fixed_size`]. This takes priority over the
+    /// size of the region set with [`TerminalDisplayBuilder::region`].
+    ///
+    /// [`TerminalDisplay::with_fixed_size`]: crate::TerminalDisplay::with_fixed_size
+    /// [`TerminalDisplayBuilder::region`]: crate::TerminalDisplayBuilder::region
+    pub fn fixed_size(mut self, width: u32, height: u32) -> Self {
+        self.fixed_size = Some(Size::new(width, height));
+        self
+    }
+
+    /// Make the display only take up `region` of the terminal, measured in
+    /// cells.
+    ///
+    /// See [`TerminalDisplay::with_region`].
+    ///
+    /// [`TerminalDisplay::with_region`]: crate::TerminalDisplay::with_region
+    pub fn region(mut self, region: Rectangle) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Set whether to hide the cursor straight away.
+    ///
+    /// It's shown again when the display is restored, unless it's been shown
+    /// or hidden with [`TerminalDisplay::set_cursor_visible`] since.
+    ///
+    /// [`TerminalDisplay::set_cursor_visible`]: crate::TerminalDisplay::set_cursor_visible
+    pub fn hide_cursor(mut self, hide: bool) -> Self {
+        self.hide_cursor = hide;
+        self
+    }
+
+    /// Set whether to switch to the alternate screen.
+    pub fn alternate_screen(mut self, alternate_screen: bool) -> Self {
+        self.alternate_screen = alternate_screen;
+        self
+    }
+
+    /// Set whether to enable raw mode.
+    pub fn raw_mode(mut self, raw_mode: bool) -> Self {
+        self.raw_mode = raw_mode;
+        self
+    }
+
+    /// Set whether to clear the part of the terminal the display covers when
+    /// it's created, which is the default.
+    ///
+    /// Otherwise, whatever was already on the terminal shows through wherever
+    /// nothing's been drawn yet, which is useful for drawing over the top of
+    /// existing content.
+    pub fn clear_screen(mut self, clear: bool) -> Self {
+        self.keep_contents = !clear;
+        self
+    }
+
+    /// Set whether to assume that every character used to draw cells is a
+    /// single column wide.
+    ///
+    /// See [`TerminalDisplay::set_assume_narrow_blocks`].
+    ///
+    /// [`TerminalDisplay::set_assume_narrow_blocks`]: crate::TerminalDisplay::set_assume_narrow_blocks
+    pub fn assume_narrow_blocks(mut self, narrow: bool) -> Self {
+        self.assume_narrow_blocks = narrow;
+        self
+    }
+
+    /// Set whether drawing outside the display is an error.
+    ///
+    /// See [`TerminalDisplay::set_strict`].
+    ///
+    /// [`TerminalDisplay::set_strict`]: crate::TerminalDisplay::set_strict
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set whether to flush after every draw.
+    ///
+    /// See [`TerminalDisplay::set_auto_flush`].
+    ///
+    /// [`TerminalDisplay::set_auto_flush`]: crate::TerminalDisplay::set_auto_flush
+    pub fn auto_flush(mut self, auto_flush: bool) -> Self {
+        self.auto_flush = auto_flush;
+        self
+    }
+
+    /// Set whether to use synchronized output.
+    ///
+    /// See [`TerminalDisplay::set_synchronized_output`].
+    ///
+    /// [`TerminalDisplay::set_synchronized_output`]: crate::TerminalDisplay::set_synchronized_output
+    pub fn synchronized_output(mut self, synchronized: bool) -> Self {
+        self.synchronized_output = synchronized;
+        self
+    }
+
+    /// Create a display which writes to stdout.
+    pub fn build(self) -> Result<TerminalDisplay<BufWriter<Stdout>>, TerminalError> {
+        self.build_with_writer(BufWriter::new(io::stdout()))
+    }
+
+    /// Create a display which writes its output to `writer` rather than to
+    /// stdout.
+    ///
+    /// See [`TerminalDisplay::with_writer`].
+    ///
+    /// [`TerminalDisplay::with_writer`]: crate::TerminalDisplay::with_writer
+    pub fn build_with_writer<W: Write>(
+        self,
+        writer: W,
+    ) -> Result<TerminalDisplay<W>, TerminalError> {
+        self.build_with(writer, SizeSource::Controlling)
+    }
+
+    /// Create a display which writes to the terminal device `tty`, and takes
+    /// its size from that terminal.
+    ///
+    /// See [`TerminalDisplay::for_tty`].
+    ///
+    /// [`TerminalDisplay::for_tty`]: crate::TerminalDisplay::for_tty
+    pub fn build_for_tty(
+        self,
+        tty: File,
+    ) -> Result<TerminalDisplay<BufWriter<File>>, TerminalError> {
+        let size_source = SizeSource::for_file(&tty);
+        self.build_with(BufWriter::new(tty), size_source)
+    }
+
+    pub(crate) fn build_with<W: Write>(
+        self,
+        writer: W,
+        size_source: SizeSource,
+    ) -> Result<TerminalDisplay<W>, TerminalError> {
+        // Anything above or to the left of the terminal can't be shown anyway.
+        let to_cells = |n: i64| n.clamp(0, u16::MAX.into()) as u16;
+        let fixed_size = self.fixed_size.or_else(|| {
+            self.region.map(|region| {
+                crate::size(
+                    &self.config,
+                    to_cells(region.size.width.into()),
+                    to_cells(region.size.height.into()),
+                )
+            })
+        });
+
+        let mut display =
+            TerminalDisplay::with_config(writer, self.config, fixed_size, size_source)?;
+        display.dithering = self.dithering;
+        display.sampling = self.sampling;
+        display.set_assume_narrow_blocks(self.assume_narrow_blocks);
+        display.set_strict(self.strict);
+        display.set_auto_flush(self.auto_flush);
+        display.set_synchronized_output(self.synchronized_output);
+        if let Some(region) = self.region {
+            display.origin = (
+                to_cells(region.top_left.x.into()),
+                to_cells(region.top_left.y.into()),
+            );
+        }
+
+        // If any of these fail, dropping the display undoes the ones which have
+        // already been done.
+        if self.alternate_screen {
+            display.stdout.enter_alternate_screen()?;
+        }
+        if self.raw_mode {
+            display.stdout.enable_raw_mode()?;
+        }
+        if self.hide_cursor {
+            display.stdout.set_cursor_hidden(true)?;
+        }
+        if !self.keep_contents {
+            // Clearing the display redraws every cell, which makes the terminal match the
+            // buffer.
+            display.clear(Color::BgColor)?;
+            display.flush()?;
+            let (origin_x, origin_y) = display.origin;
+            display.stdout.move_to(origin_x, origin_y)?;
+            display.stdout.flush()?;
+        }
+        Ok(display)
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+use crate::{Color, RenderMode, MAX_CELL_PIXELS};
+
+/// The new contents of a cell which has changed since the display was last
+/// flushed, from [`TerminalDisplay::dirty_cells`].
+///
+/// [`TerminalDisplay::dirty_cells`]: crate::TerminalDisplay::dirty_cells
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CellUpdate {
+    pub(crate) column: u16,
+    pub(crate) row: u16,
+    pub(crate) render_mode: RenderMode,
+    pub(crate) pixels: [Color; MAX_CELL_PIXELS],
+    pub(crate) attributes: Attributes,
+}
+
+/// A cell which has had text written over it with
+/// [`TerminalDisplay::put_str`], rather than being drawn from pixels.
+///
+/// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TextCell {
+    pub(crate) character: char,
+    pub(crate) fg: Color,
+    pub(crate) bg: Color,
+    /// Whether the cell's been written to the terminal as it is now.
+    pub(crate) drawn: bool,
+}
+
+impl CellUpdate {
+    /// The column of the cell, relative to the top-left of the display.
+    pub fn column(&self) -> u16 {
+        self.column
+    }
+
+    /// The row of the cell, relative to the top-left of the display.
+    pub fn row(&self) -> u16 {
+        self.row
+    }
+
+    /// The colors of the pixels in the cell in row-major order, as they should
+    /// be displayed in the display's color mode.
+    ///
+    /// For [`RenderMode::HalfBlock`], this is the top pixel followed by the
+    /// bottom pixel.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels[..self.render_mode.cell_pixel_count()]
+    }
+
+    /// The text attributes the cell is drawn with, set with
+    /// [`TerminalDisplay::set_attr`].
+    ///
+    /// [`TerminalDisplay::set_attr`]: crate::TerminalDisplay::set_attr
+    pub fn attributes(&self) -> Attributes {
+        self.attributes
+    }
+}
+
+/// A set of text attributes which a cell can be drawn with, using
+/// [`TerminalDisplay::set_attr`].
+///
+/// These can be combined with `|`, e.g. `Attributes::BOLD | Attributes::BLINK`.
+///
+/// [`TerminalDisplay::set_attr`]: crate::TerminalDisplay::set_attr
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Attributes(u8);
+
+impl Attributes {
+    /// No attributes.
+    pub const NONE: Attributes = Attributes(0);
+    /// Bold, which some terminals draw brighter instead.
+    pub const BOLD: Attributes = Attributes(1 << 0);
+    /// Blinking.
+    pub const BLINK: Attributes = Attributes(1 << 1);
+    /// Underlined.
+    pub const UNDERLINE: Attributes = Attributes(1 << 2);
+    /// Reversed, which swaps the foreground and background colors.
+    pub const REVERSE: Attributes = Attributes(1 << 3);
+
+    /// Every attribute, along with its name.
+    const ALL: [(Attributes, &'static str); 4] = [
+        (Attributes::BOLD, "BOLD"),
+        (Attributes::BLINK, "BLINK"),
+        (Attributes::UNDERLINE, "UNDERLINE"),
+        (Attributes::REVERSE, "REVERSE"),
+    ];
+
+    /// Check whether this includes all of `other`.
+    pub fn contains(self, other: Attributes) -> bool {
+        self & other == other
+    }
+
+    /// Check whether this doesn't include any attributes.
+    pub fn is_empty(self) -> bool {
+        self == Attributes::NONE
+    }
+
+    /// Iterate over each of the individual attributes this includes.
+    pub(crate) fn iter(self) -> impl Iterator<Item = Attributes> {
+        Attributes::ALL
+            .into_iter()
+            .map(|(attribute, _)| attribute)
+            .filter(move |&attribute| self.contains(attribute))
+    }
+}
+
+impl BitOr for Attributes {
+    type Output = Attributes;
+
+    fn bitor(self, other: Attributes) -> Attributes {
+        Attributes(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Attributes {
+    fn bitor_assign(&mut self, other: Attributes) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitAnd for Attributes {
+    type Output = Attributes;
+
+    fn bitand(self, other: Attributes) -> Attributes {
+        Attributes(self.0 & other.0)
+    }
+}
+
+impl fmt::Debug for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("Attributes::NONE");
+        }
+        let mut first = true;
+        for (attribute, name) in Attributes::ALL {
+            if self.contains(attribute) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                write!(f, "Attributes::{name}")?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
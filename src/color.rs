@@ -1,16 +1,25 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
 use crossterm::style::Color as CrosstermColor;
 use embedded_graphics_core::pixelcolor::{
-    Bgr555, Bgr565, Bgr888, BinaryColor, Gray2, Gray4, Gray8, Rgb555, Rgb565, Rgb888,
+    Bgr555, Bgr565, Bgr666, Bgr888, BinaryColor, Gray2, Gray4, Gray8, Rgb555, Rgb565, Rgb666,
+    Rgb888,
 };
 use embedded_graphics_core::prelude::*;
 
+use crate::quantize;
+
 /// A color which can be rendered to a terminal.
 ///
 /// Basically a clone of [`crossterm::style::Color`], which can't be used
 /// directly because of the orphan rule.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub enum Color {
     /// The default background color of the terminal.
+    #[default]
     BgColor,
 
     /// The default foreground color of the terminal.
@@ -66,6 +75,10 @@ pub enum Color {
 
     /// An RGB color. See [RGB color model](https://en.wikipedia.org/wiki/RGB_color_model) for more info.
     ///
+    /// The components are gamma-encoded sRGB, which is what terminals expect;
+    /// anything that combines colors converts them to linear RGB first (see
+    /// [`Color::to_linear`]).
+    ///
     /// Most UNIX terminals and Windows 10 supported only.
     /// See [Platform-specific notes](enum.Color.html#platform-specific-notes)
     /// for more info.
@@ -77,9 +90,312 @@ pub enum Color {
     /// See [Platform-specific notes](enum.Color.html#platform-specific-notes)
     /// for more info.
     AnsiValue(u8),
+
+    /// An RGB color with an alpha channel, which gets blended over whatever
+    /// was previously drawn underneath it.
+    ///
+    /// Like [`Color::Rgb`], the components are sRGB. The alpha is linear,
+    /// since it's a proportion of light rather than a brightness.
+    ///
+    /// An alpha of 0 is fully transparent, and leaves what's underneath as it
+    /// is; an alpha of 255 is fully opaque, and is the same as [`Color::Rgb`].
+    ///
+    /// See [`Color::blend_over`] for how exactly colors are blended.
+    Rgba(Rgb888, u8),
+
+    /// A fully transparent color, which leaves whatever was previously drawn
+    /// underneath it exactly as it is.
+    ///
+    /// This is useful for drawing sprites with holes in them. Unlike
+    /// [`Color::BgColor`], which draws the terminal's default background color,
+    /// this doesn't change anything at all.
+    Transparent,
 }
 
 impl Color {
+    /// Blend this color over `background`.
+    ///
+    /// This only does anything for [`Color::Rgba`] and [`Color::Transparent`];
+    /// every other color is opaque, and so just replaces the background. [`Color::Rgba`] is blended
+    /// using the standard 'over' operator in linear RGB, giving an opaque color.
+    ///
+    /// The terminal's actual default colors aren't known, so
+    /// [`Color::BgColor`] is assumed to be black and [`Color::FgColor`] is
+    /// assumed to be white. Blending over [`Color::Transparent`] leaves this
+    /// color as it is.
+    pub fn blend_over(self, background: Color) -> Color {
+        let (color, alpha) = match self {
+            Color::Rgba(_, 0) | Color::Transparent => return background,
+            Color::Rgba(color, 255) => return Color::Rgb(color),
+            Color::Rgba(color, alpha) => (color, alpha),
+            color => return color,
+        };
+
+        let background = match background {
+            // There's nothing underneath to blend with.
+            Color::Transparent => return self,
+            background => background.resolved_linear_rgb(),
+        };
+
+        let alpha = f32::from(alpha) / 255.0;
+        background
+            .lerp(LinearRgb::from_srgb(color), alpha)
+            .to_color()
+    }
+
+    /// Get the color from the 256-color palette's 6x6x6 color cube with the
+    /// given red, green and blue levels, each from 0 to 5.
+    ///
+    /// This gives a [`Color::AnsiValue`] from 16 to 231, which terminals with
+    /// 256 colors can display exactly, rather than an RGB color having to be
+    /// matched to it. Levels above 5 are clamped to 5.
+    pub fn ansi_rgb(r: u8, g: u8, b: u8) -> Color {
+        let (r, g, b) = (r.min(5), g.min(5), b.min(5));
+        Color::AnsiValue(16 + 36 * r + 6 * g + b)
+    }
+
+    /// Get the gray from the 256-color palette's grayscale ramp with the given
+    /// level, from 0 (the darkest) to 23 (the lightest).
+    ///
+    /// This gives a [`Color::AnsiValue`] from 232 to 255. The ramp doesn't
+    /// include pure black or white, which are in the color cube instead (see
+    /// [`Color::ansi_rgb`]). Levels above 23 are clamped to 23.
+    pub fn ansi_gray(level: u8) -> Color {
+        Color::AnsiValue(232 + level.min(23))
+    }
+
+    /// Parse a hex color like `#ff8000`, returning a [`Color::Rgb`].
+    ///
+    /// The leading `#` is optional, and the shorthand form `#f80` is also
+    /// accepted.
+    pub fn from_hex(s: &str) -> Result<Color, ParseColorError> {
+        let digits = s.strip_prefix('#').unwrap_or(s);
+        if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(ParseColorError(()));
+        }
+        // We've just checked that these are all ASCII, so slicing by bytes is fine.
+        let parse = |digits: &str| u8::from_str_radix(digits, 16).unwrap();
+        let (r, g, b) = match digits.len() {
+            6 => (
+                parse(&digits[0..2]),
+                parse(&digits[2..4]),
+                parse(&digits[4..6]),
+            ),
+            // Each digit of the shorthand form is repeated, so `f` is `ff`.
+            3 => (
+                parse(&digits[0..1]) * 0x11,
+                parse(&digits[1..2]) * 0x11,
+                parse(&digits[2..3]) * 0x11,
+            ),
+            _ => return Err(ParseColorError(())),
+        };
+        Ok(Color::Rgb(Rgb888::new(r, g, b)))
+    }
+
+    /// Get the RGB value of this color, if it has a known one.
+    ///
+    /// The named colors and [`Color::AnsiValue`] are resolved using the
+    /// standard xterm palette, although the terminal may really use different
+    /// ones. The alpha of [`Color::Rgba`] is ignored.
+    ///
+    /// Returns `None` for [`Color::BgColor`] and [`Color::FgColor`], since the
+    /// terminal's default colors aren't known, and for [`Color::Transparent`].
+    pub fn to_rgb888(self) -> Option<Rgb888> {
+        match self {
+            Color::BgColor | Color::FgColor | Color::Transparent => None,
+            Color::Rgb(rgb) | Color::Rgba(rgb, _) => Some(rgb),
+            Color::AnsiValue(n) => Some(quantize::ansi_256_rgb(n)),
+            named => quantize::ANSI_16
+                .iter()
+                .find(|&&(color, _)| color == named)
+                .map(|&(_, rgb)| rgb),
+        }
+    }
+
+    /// Get this color's red, green and blue components in sRGB, from 0 to 1.
+    ///
+    /// This is the same as [`Color::to_rgb888`], just scaled down to between 0
+    /// and 1 to match [`Color::to_linear`]. The components are gamma-encoded,
+    /// which is what terminals expect, but not what colors should be combined
+    /// in.
+    ///
+    /// Returns `None` for [`Color::BgColor`] and [`Color::FgColor`], since the
+    /// terminal's default colors aren't known, and for [`Color::Transparent`].
+    pub fn to_srgb(self) -> Option<[f32; 3]> {
+        let color = self.to_rgb888()?;
+        Some([color.r(), color.g(), color.b()].map(|component| f32::from(component) / 255.0))
+    }
+
+    /// Get this color's red, green and blue components in linear RGB, from 0
+    /// to 1.
+    ///
+    /// Colors are stored in sRGB, where the components are gamma-encoded; but
+    /// to average or blend colors correctly, it has to be done in linear RGB,
+    /// otherwise the results come out too dark.
+    ///
+    /// Returns `None` for [`Color::BgColor`] and [`Color::FgColor`], since the
+    /// terminal's default colors aren't known, and for [`Color::Transparent`].
+    pub fn to_linear(self) -> Option<[f32; 3]> {
+        Some(LinearRgb::from_srgb(self.to_rgb888()?).0)
+    }
+
+    /// Get the relative luminance of this color, from 0 for black to 1 for
+    /// white.
+    ///
+    /// This uses the Rec. 709 coefficients, applied in linear RGB. The
+    /// terminal's actual default colors aren't known, so [`Color::BgColor`] is
+    /// assumed to be black and [`Color::FgColor`] is assumed to be white.
+    /// [`Color::Transparent`] has no luminance of its own, and gives 0.
+    pub fn luminance(self) -> f32 {
+        let LinearRgb([r, g, b]) = self.resolved_linear_rgb();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// Get a foreground color which is readable on top of this color as a
+    /// background, for drawing text over it.
+    ///
+    /// This is either [`Color::Black`] or [`Color::White`], whichever has the
+    /// higher WCAG contrast ratio against this color's
+    /// [luminance](Color::luminance). The terminal's default colors are
+    /// already meant to be readable on top of each other, so
+    /// [`Color::BgColor`] and [`Color::Transparent`] (which shows the
+    /// terminal's background) give [`Color::FgColor`], and [`Color::FgColor`]
+    /// gives [`Color::BgColor`].
+    pub fn readable_fg(self) -> Color {
+        match self {
+            Color::BgColor | Color::Transparent => Color::FgColor,
+            Color::FgColor => Color::BgColor,
+            color => {
+                let luminance = color.luminance();
+                // The contrast ratio between two colors is (L1 + 0.05) / (L2 + 0.05), where
+                // L1 is the luminance of the lighter one; black has a luminance of 0 and white
+                // has a luminance of 1.
+                let black_contrast = (luminance + 0.05) / 0.05;
+                let white_contrast = 1.05 / (luminance + 0.05);
+                if black_contrast > white_contrast {
+                    Color::Black
+                } else {
+                    Color::White
+                }
+            }
+        }
+    }
+
+    /// Create a [`Color::Rgb`] from red, green and blue components in linear
+    /// RGB, from 0 to 1.
+    ///
+    /// Components outside of that range are clamped to it.
+    pub fn from_linear(color: [f32; 3]) -> Color {
+        LinearRgb(color).to_color()
+    }
+
+    /// Mix this color with `other`, going from this color when `t` is 0 to
+    /// `other` when `t` is 1, giving a [`Color::Rgb`].
+    ///
+    /// `t` is clamped to between 0 and 1. Like [`Color::blend_over`], this is
+    /// done in linear RGB rather than on the sRGB values from
+    /// [`Color::to_rgb888`], since mixing those gives results which are too
+    /// dark: mixing red and blue halfway gives `Rgb(188, 0, 188)`, not
+    /// `Rgb(128, 0, 128)`.
+    ///
+    /// [`Color::BgColor`] is assumed to be black and [`Color::FgColor`] is
+    /// assumed to be white; [`Color::Transparent`] is treated as black too,
+    /// and the alpha of [`Color::Rgba`] is ignored.
+    pub fn mix(self, other: Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        self.resolved_linear_rgb()
+            .lerp(other.resolved_linear_rgb(), t)
+            .to_color()
+    }
+
+    /// Multiply the brightness of this color by `factor`, giving a
+    /// [`Color::Rgb`]; this is useful for fading colors in and out.
+    ///
+    /// This multiplies each component in linear RGB, so that a factor of 0.5
+    /// gives a color which is half as bright; that isn't the same as halving
+    /// the sRGB values, so `Rgb(200, 0, 0)` scaled by 0.5 is
+    /// `Rgb(146, 0, 0)`, not `Rgb(100, 0, 0)`. Components which end up above
+    /// the maximum are clamped to it, and negative factors give black. Other
+    /// colors are resolved the same way as in [`Color::mix`].
+    pub fn scale(self, factor: f32) -> Color {
+        self.resolved_linear_rgb()
+            .map(|component| component * factor)
+            .to_color()
+    }
+
+    /// Add `other` to this color, like shining two lights on the same spot,
+    /// giving a [`Color::Rgb`].
+    ///
+    /// This adds each component in linear RGB rather than adding the sRGB
+    /// values, clamping them to the maximum; for example, `Rgb(100, 100, 100)`
+    /// added to itself is `Rgb(138, 138, 138)`. Other colors are resolved the
+    /// same way as in [`Color::mix`].
+    pub fn saturating_add(self, other: Color) -> Color {
+        self.resolved_linear_rgb()
+            .zip(other.resolved_linear_rgb(), |a, b| a + b)
+            .to_color()
+    }
+
+    /// Get this color's components in linear RGB like
+    /// [`Color::to_linear`], assuming the terminal's default colors are
+    /// black and white and treating [`Color::Transparent`] as black.
+    fn resolved_linear_rgb(self) -> LinearRgb {
+        match self {
+            Color::BgColor | Color::Transparent => LinearRgb::BLACK,
+            Color::FgColor => LinearRgb::WHITE,
+            color => LinearRgb::from_srgb(color.to_rgb888().unwrap()),
+        }
+    }
+
+    /// Get this color as it should be drawn over a pixel which it only covers
+    /// `coverage` (from 0 to 1) of, which is a [`Color::Rgba`] with its alpha
+    /// scaled down by the coverage.
+    ///
+    /// Full coverage gives the color itself. Otherwise, the terminal's
+    /// default colors are assumed to be black and white, like in
+    /// [`Color::blend_over`].
+    pub(crate) fn with_coverage(self, coverage: f32) -> Color {
+        let (rgb, alpha) = match self {
+            _ if coverage >= 1.0 => return self,
+            Color::Transparent => return self,
+            Color::Rgba(rgb, alpha) => (rgb, alpha),
+            Color::BgColor => (Rgb888::BLACK, 255),
+            Color::FgColor => (Rgb888::WHITE, 255),
+            // Every other color has an RGB value.
+            color => (color.to_rgb888().unwrap(), 255),
+        };
+        Color::Rgba(rgb, (f32::from(alpha) * coverage.max(0.0)).round() as u8)
+    }
+
+    /// Get the kebab-case name [`FromStr`] parses this color from, if it's one
+    /// of the named colors.
+    #[cfg(feature = "serde")]
+    pub(crate) fn name(self) -> Option<&'static str> {
+        let name = match self {
+            Color::BgColor => "bg-color",
+            Color::FgColor => "fg-color",
+            Color::Black => "black",
+            Color::DarkGrey => "dark-grey",
+            Color::Red => "red",
+            Color::DarkRed => "dark-red",
+            Color::Green => "green",
+            Color::DarkGreen => "dark-green",
+            Color::Yellow => "yellow",
+            Color::DarkYellow => "dark-yellow",
+            Color::Blue => "blue",
+            Color::DarkBlue => "dark-blue",
+            Color::Magenta => "magenta",
+            Color::DarkMagenta => "dark-magenta",
+            Color::Cyan => "cyan",
+            Color::DarkCyan => "dark-cyan",
+            Color::White => "white",
+            Color::Grey => "grey",
+            Color::Transparent => "transparent",
+            Color::Rgb(_) | Color::Rgba(..) | Color::AnsiValue(_) => return None,
+        };
+        Some(name)
+    }
+
     pub(crate) fn to_crossterm_color(self) -> CrosstermColor {
         match self {
             Color::BgColor | Color::FgColor => CrosstermColor::Reset,
@@ -99,26 +415,197 @@ impl Color {
             Color::DarkCyan => CrosstermColor::DarkCyan,
             Color::White => CrosstermColor::White,
             Color::Grey => CrosstermColor::Grey,
-            Color::Rgb(color) => CrosstermColor::Rgb {
+            // Translucent colors get blended before they're stored, so they shouldn't
+            // ever get here; but if they do, just draw them as opaque.
+            Color::Rgb(color) | Color::Rgba(color, _) => CrosstermColor::Rgb {
                 r: color.r(),
                 g: color.g(),
                 b: color.b(),
             },
+            // Transparent pixels are never stored either, since blending them leaves the
+            // pixel as it was.
+            Color::Transparent => CrosstermColor::Reset,
             Color::AnsiValue(n) => CrosstermColor::AnsiValue(n),
         }
     }
 }
 
-impl PixelColor for Color {
-    type Raw = ();
+/// How a color which gets drawn combines with whatever's already been drawn
+/// underneath it.
+///
+/// Apart from [`BlendMode::Normal`], these all work on each component in
+/// linear RGB, and always give a [`Color::Rgb`]. Like [`Color::mix`], the
+/// terminal's default background and foreground colors are assumed to be
+/// black and white. The alpha of [`Color::Rgba`] scales how much effect the
+/// color has, and [`Color::Transparent`] never has any.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Draw the color over what's underneath, replacing it unless it's
+    /// translucent: see [`Color::blend_over`].
+    #[default]
+    Normal,
+
+    /// Multiply the color by what's underneath, which darkens it. White
+    /// leaves it as it is, and black makes it black; this is good for shadows
+    /// and tinting.
+    Multiply,
+
+    /// Add the color to what's underneath, like shining a light on it, which
+    /// lightens it. Black leaves it as it is. This is the same as
+    /// [`Color::saturating_add`], and is good for glows and particles.
+    Add,
+
+    /// Lighten what's underneath by multiplying the inverses of the two colors
+    /// together and inverting the result. Black leaves it as it is, and white
+    /// makes it white, but unlike [`BlendMode::Add`] it never gets any lighter
+    /// than that, so it's gentler on colors which are already light.
+    Screen,
 }
 
-impl Default for Color {
-    fn default() -> Self {
-        Color::BgColor
+impl BlendMode {
+    /// Combine `color` with `background` using this blend mode.
+    pub fn blend(self, color: Color, background: Color) -> Color {
+        let alpha = match color {
+            Color::Transparent | Color::Rgba(_, 0) => return background,
+            _ if self == BlendMode::Normal => return color.blend_over(background),
+            Color::Rgba(_, alpha) => f32::from(alpha) / 255.0,
+            _ => 1.0,
+        };
+        let background = background.resolved_linear_rgb();
+        let blended = color
+            .resolved_linear_rgb()
+            .zip(background, |a, b| match self {
+                BlendMode::Normal => a,
+                BlendMode::Multiply => a * b,
+                BlendMode::Add => a + b,
+                BlendMode::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+            })
+            .map(|component| component.min(1.0));
+        background.lerp(blended, alpha).to_color()
+    }
+}
+
+/// Parses either a hex color (see [`Color::from_hex`]) or the name of one of
+/// the named colors in kebab-case, e.g. `red` or `dark-blue`.
+///
+/// Names are case-insensitive. `bg-color` and `fg-color` give the terminal's
+/// default colors, and `transparent` gives [`Color::Transparent`].
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let color = match s.to_ascii_lowercase().as_str() {
+            "bg-color" => Color::BgColor,
+            "fg-color" => Color::FgColor,
+            "black" => Color::Black,
+            "dark-grey" | "dark-gray" => Color::DarkGrey,
+            "red" => Color::Red,
+            "dark-red" => Color::DarkRed,
+            "green" => Color::Green,
+            "dark-green" => Color::DarkGreen,
+            "yellow" => Color::Yellow,
+            "dark-yellow" => Color::DarkYellow,
+            "blue" => Color::Blue,
+            "dark-blue" => Color::DarkBlue,
+            "magenta" => Color::Magenta,
+            "dark-magenta" => Color::DarkMagenta,
+            "cyan" => Color::Cyan,
+            "dark-cyan" => Color::DarkCyan,
+            "white" => Color::White,
+            "grey" | "gray" => Color::Grey,
+            "transparent" => Color::Transparent,
+            _ => return Color::from_hex(s),
+        };
+        Ok(color)
     }
 }
 
+/// An error from parsing a [`Color`] from a string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorError(());
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid color")
+    }
+}
+
+impl Error for ParseColorError {}
+
+/// A color in linear RGB, with each component from 0 to 1.
+///
+/// [`Color`]s are stored in sRGB, which is only right for sending to the
+/// terminal; everything which blends or averages colors converts them to this
+/// first, and only converts the result back into a [`Color`] at the end.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct LinearRgb(pub(crate) [f32; 3]);
+
+impl LinearRgb {
+    pub(crate) const BLACK: LinearRgb = LinearRgb([0.0; 3]);
+    pub(crate) const WHITE: LinearRgb = LinearRgb([1.0; 3]);
+
+    /// Convert `rgb` to linear RGB, using a lookup table rather than working
+    /// out the transfer function every time, since this gets done for every
+    /// color in a palette for every pixel when quantizing.
+    pub(crate) fn from_srgb(rgb: Rgb888) -> LinearRgb {
+        static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+        let table = TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as u8)));
+        LinearRgb([rgb.r(), rgb.g(), rgb.b()].map(|component| table[usize::from(component)]))
+    }
+
+    /// Convert this back to a [`Color::Rgb`], clamping the components to
+    /// between 0 and 1.
+    pub(crate) fn to_color(self) -> Color {
+        let [r, g, b] = self.0.map(linear_to_srgb);
+        Color::Rgb(Rgb888::new(r, g, b))
+    }
+
+    /// Apply `f` to each component.
+    pub(crate) fn map(self, f: impl Fn(f32) -> f32) -> LinearRgb {
+        LinearRgb(self.0.map(f))
+    }
+
+    /// Combine each component with the same component of `other` using `f`.
+    pub(crate) fn zip(self, other: LinearRgb, f: impl Fn(f32, f32) -> f32) -> LinearRgb {
+        LinearRgb(std::array::from_fn(|i| f(self.0[i], other.0[i])))
+    }
+
+    /// Interpolate between this color when `t` is 0 and `other` when `t` is 1.
+    pub(crate) fn lerp(self, other: LinearRgb, t: f32) -> LinearRgb {
+        self.zip(other, |a, b| a + (b - a) * t)
+    }
+
+    /// Get the squared distance between this color and `other`.
+    pub(crate) fn distance_squared(self, other: LinearRgb) -> f32 {
+        (0..3).map(|i| (self.0[i] - other.0[i]).powi(2)).sum()
+    }
+}
+
+/// Convert an sRGB component to linear RGB, using the sRGB transfer function.
+fn srgb_to_linear(component: u8) -> f32 {
+    let component = f32::from(component) / 255.0;
+    if component <= 0.04045 {
+        component / 12.92
+    } else {
+        ((component + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear RGB component back to sRGB.
+fn linear_to_srgb(component: f32) -> u8 {
+    let component = component.clamp(0.0, 1.0);
+    let component = if component <= 0.0031308 {
+        component * 12.92
+    } else {
+        1.055 * component.powf(1.0 / 2.4) - 0.055
+    };
+    (component * 255.0).round() as u8
+}
+
+impl PixelColor for Color {
+    type Raw = ();
+}
+
 impl From<BinaryColor> for Color {
     fn from(color: BinaryColor) -> Self {
         match color {
@@ -181,3 +668,162 @@ impl From<Rgb565> for Color {
         Self::Rgb(color.into())
     }
 }
+
+impl From<Rgb666> for Color {
+    fn from(color: Rgb666) -> Self {
+        Self::Rgb(rgb666_to_rgb888(color))
+    }
+}
+
+impl From<Bgr666> for Color {
+    fn from(color: Bgr666) -> Self {
+        Self::Rgb(rgb666_to_rgb888(color))
+    }
+}
+
+/// Convert an 18-bit color to an `Rgb888`.
+///
+/// `embedded_graphics_core` doesn't provide this conversion itself, so this
+/// does the same scaling that its other conversions do.
+fn rgb666_to_rgb888(color: impl RgbColor) -> Rgb888 {
+    let scale = |channel: u8| ((u16::from(channel) * 255 + 31) / 63) as u8;
+    Rgb888::new(scale(color.r()), scale(color.g()), scale(color.b()))
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics_core::pixelcolor::{Gray8, Rgb888};
+    use embedded_graphics_core::prelude::*;
+
+    use super::{BlendMode, Color};
+
+    #[test]
+    fn blend_half_alpha() {
+        // Half of white's light over black is 50% in linear RGB, which is 188 in sRGB.
+        assert_eq!(
+            Color::Rgba(Rgb888::WHITE, 128).blend_over(Color::Rgb(Rgb888::BLACK)),
+            Color::Rgb(Rgb888::new(188, 188, 188))
+        );
+        assert_eq!(
+            Color::Rgba(Rgb888::new(255, 0, 0), 128).blend_over(Color::Rgb(Rgb888::new(0, 0, 255))),
+            Color::Rgb(Rgb888::new(188, 0, 187))
+        );
+        // The default background is treated as black.
+        assert_eq!(
+            Color::Rgba(Rgb888::WHITE, 128).blend_over(Color::BgColor),
+            Color::Rgb(Rgb888::new(188, 188, 188))
+        );
+    }
+
+    #[test]
+    fn linear_midtone() {
+        // Naively averaging the sRGB values of black and white would give 128, which
+        // looks too dark.
+        let midtone = Color::from_linear([0.5; 3]);
+        assert_eq!(midtone, Color::Rgb(Rgb888::new(188, 188, 188)));
+        assert_eq!(Color::from(Gray8::new(0)).to_linear(), Some([0.0; 3]));
+        assert_eq!(Color::from(Gray8::new(255)).to_linear(), Some([1.0; 3]));
+        assert_eq!(Color::BgColor.to_linear(), None);
+
+        // Converting to and from linear RGB doesn't lose anything.
+        for value in 0..=255 {
+            let color = Color::from(Gray8::new(value));
+            assert_eq!(Color::from_linear(color.to_linear().unwrap()), color);
+        }
+    }
+
+    #[test]
+    fn srgb_and_linear_components() {
+        let color = Color::Rgb(Rgb888::new(0, 51, 255));
+        assert_eq!(color.to_srgb(), Some([0.0, 0.2, 1.0]));
+        let [r, g, b] = color.to_linear().unwrap();
+        assert!((g - 0.0331).abs() < 0.0001);
+        assert_eq!((r, b), (0.0, 1.0));
+
+        // Named colors are resolved with the xterm palette, like `to_rgb888`.
+        assert_eq!(Color::Red.to_srgb(), Some([1.0, 0.0, 0.0]));
+        assert_eq!(Color::Red.to_linear(), Some([1.0, 0.0, 0.0]));
+        for color in [Color::BgColor, Color::FgColor, Color::Transparent] {
+            assert_eq!(color.to_srgb(), None);
+            assert_eq!(color.to_linear(), None);
+        }
+    }
+
+    #[test]
+    fn ansi_cube_and_gray() {
+        assert_eq!(Color::ansi_rgb(0, 0, 0), Color::AnsiValue(16));
+        assert_eq!(Color::ansi_rgb(0, 0, 5), Color::AnsiValue(21));
+        assert_eq!(Color::ansi_rgb(0, 5, 0), Color::AnsiValue(46));
+        assert_eq!(Color::ansi_rgb(5, 0, 0), Color::AnsiValue(196));
+        assert_eq!(Color::ansi_rgb(5, 5, 5), Color::AnsiValue(231));
+        // Components above 5 are clamped.
+        assert_eq!(Color::ansi_rgb(255, 6, 5), Color::AnsiValue(231));
+
+        assert_eq!(Color::ansi_gray(0), Color::AnsiValue(232));
+        assert_eq!(Color::ansi_gray(23), Color::AnsiValue(255));
+        assert_eq!(Color::ansi_gray(255), Color::AnsiValue(255));
+    }
+
+    #[test]
+    fn linear_average() {
+        // sRGB's 128 is much less than half as bright as 255.
+        let [r, g, b] = Color::Rgb(Rgb888::new(128, 0, 255)).to_linear().unwrap();
+        assert!((r - 0.2158).abs() < 0.0001);
+        assert_eq!((g, b), (0.0, 1.0));
+
+        // So averaging red and blue in linear RGB gives a brighter purple than
+        // averaging their sRGB components would.
+        let red = Color::Rgb(Rgb888::new(255, 0, 0));
+        let blue = Color::Rgb(Rgb888::new(0, 0, 255));
+        let srgb_average = Color::Rgb(Rgb888::new(128, 0, 128));
+        let linear_average = red.mix(blue, 0.5);
+        assert_eq!(linear_average, Color::Rgb(Rgb888::new(188, 0, 188)));
+        assert_ne!(linear_average, srgb_average);
+    }
+
+    #[test]
+    fn scale_clamps() {
+        let red = Color::Rgb(Rgb888::new(200, 0, 0));
+        // Halving the light doesn't halve the sRGB value.
+        assert_eq!(red.scale(0.5), Color::Rgb(Rgb888::new(146, 0, 0)));
+        assert_eq!(red.scale(1.0), red);
+        assert_eq!(
+            Color::Rgb(Rgb888::new(100, 50, 200)).scale(2.0),
+            Color::Rgb(Rgb888::new(138, 71, 255))
+        );
+        assert_eq!(red.scale(10.0), Color::Rgb(Rgb888::new(255, 0, 0)));
+        assert_eq!(red.scale(-1.0), Color::Rgb(Rgb888::BLACK));
+        assert_eq!(Color::FgColor.scale(0.0), Color::Rgb(Rgb888::BLACK));
+    }
+
+    #[test]
+    fn saturating_add_clamps() {
+        let gray = Color::Rgb(Rgb888::new(100, 100, 100));
+        assert_eq!(
+            gray.saturating_add(gray),
+            Color::Rgb(Rgb888::new(138, 138, 138))
+        );
+        assert_eq!(
+            Color::Rgb(Rgb888::new(200, 0, 0)).saturating_add(Color::Rgb(Rgb888::new(200, 0, 100))),
+            Color::Rgb(Rgb888::new(255, 0, 100))
+        );
+        // The default background is black, so adding it changes nothing, and
+        // adding the default foreground gives white.
+        assert_eq!(gray.saturating_add(Color::BgColor), gray);
+        assert_eq!(
+            gray.saturating_add(Color::FgColor),
+            Color::Rgb(Rgb888::WHITE)
+        );
+    }
+
+    #[test]
+    fn blend_over_transparent() {
+        let color = Color::Rgba(Rgb888::WHITE, 128);
+        assert_eq!(color.blend_over(Color::Transparent), color);
+        assert_eq!(BlendMode::Normal.blend(color, Color::Transparent), color);
+        assert_eq!(
+            Color::Rgba(Rgb888::WHITE, 0).blend_over(Color::Transparent),
+            Color::Transparent
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use embedded_graphics_core::geometry::{Point, Size};
+
+/// An error from drawing to the terminal.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TerminalError {
+    /// Writing to the terminal, or querying its state, failed.
+    Io(io::Error),
+
+    /// The terminal is too small for what was asked of it: see
+    /// [`TerminalDisplay::require_size`].
+    ///
+    /// [`TerminalDisplay::require_size`]: crate::TerminalDisplay::require_size
+    TerminalTooSmall {
+        /// The size of the terminal as (columns, rows).
+        have: (u16, u16),
+        /// The smallest size the terminal needed to be as (columns, rows).
+        need: (u16, u16),
+    },
+
+    /// Something was asked for which isn't supported, either by this crate or
+    /// by the terminal, described by the contained message.
+    Unsupported(&'static str),
+
+    /// Something was drawn outside the bounds of the display, while it was in
+    /// strict mode: see [`TerminalDisplay::set_strict`].
+    ///
+    /// [`TerminalDisplay::set_strict`]: crate::TerminalDisplay::set_strict
+    OutOfBounds {
+        /// The first point found outside the display, in drawing coordinates.
+        point: Point,
+    },
+
+    /// A [`BufferSnapshot`] couldn't be restored, because the display has
+    /// changed size since it was taken.
+    ///
+    /// [`BufferSnapshot`]: crate::BufferSnapshot
+    SizeMismatch {
+        /// The size of the display in pixels when the snapshot was taken.
+        expected: Size,
+        /// The size of the display in pixels now.
+        actual: Size,
+    },
+}
+
+impl fmt::Display for TerminalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminalError::Io(error) => write!(f, "terminal I/O failed: {error}"),
+            TerminalError::TerminalTooSmall { have, need } => write!(
+                f,
+                "the terminal is {}x{} cells, but needs to be at least {}x{} cells",
+                have.0, have.1, need.0, need.1
+            ),
+            TerminalError::Unsupported(message) => write!(f, "unsupported: {message}"),
+            TerminalError::OutOfBounds { point } => write!(
+                f,
+                "tried to draw at ({}, {}), outside the display",
+                point.x, point.y
+            ),
+            TerminalError::SizeMismatch { expected, actual } => write!(
+                f,
+                "the display is {}x{} pixels, but was {}x{} pixels when the snapshot was taken",
+                actual.width, actual.height, expected.width, expected.height
+            ),
+        }
+    }
+}
+
+impl Error for TerminalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TerminalError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TerminalError {
+    fn from(error: io::Error) -> Self {
+        TerminalError::Io(error)
+    }
+}
+
+/// This allows `?` to still be used on the display's methods in functions
+/// which return `io::Result`.
+impl From<TerminalError> for io::Error {
+    fn from(error: TerminalError) -> Self {
+        match error {
+            TerminalError::Io(error) => error,
+            error => io::Error::other(error),
+        }
+    }
+}
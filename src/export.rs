@@ -0,0 +1,129 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::prelude::*;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, ImageError, Rgb, RgbImage, Rgba, RgbaImage};
+
+use crate::render::RenderConfig;
+use crate::{buffer_size, Color, TerminalError};
+
+/// Render `buffer` to an image with one pixel per pixel of the display, as it
+/// would be shown by a terminal using `config`.
+///
+/// `default_colors` are the RGB values to use for the terminal's default
+/// (background, foreground) colors.
+pub(crate) fn to_image(
+    buffer: &[Vec<Color>],
+    config: &RenderConfig,
+    default_colors: (Rgb888, Rgb888),
+) -> RgbImage {
+    let (width, height) = buffer_size(buffer);
+    let size = Size::new(width as u32, height as u32);
+    RgbImage::from_fn(width as u32, height as u32, |x, y| {
+        let point = config
+            .orientation
+            .apply(size, Point::new(x as i32, y as i32));
+        let color = config.display_rgb(buffer[point.y as usize][point.x as usize], default_colors);
+        Rgb([color.r(), color.g(), color.b()])
+    })
+}
+
+/// Load the image at `path`, returning its pixels in row-major order along
+/// with its size.
+///
+/// Translucent pixels are blended over `background`.
+pub(crate) fn load_image(
+    path: impl AsRef<Path>,
+    background: Rgb888,
+) -> Result<(Vec<Color>, Size), TerminalError> {
+    let image = image::open(path).map_err(image_error)?.into_rgba8();
+    let size = Size::new(image.width(), image.height());
+    let background = Color::Rgb(background);
+    let pixels = image
+        .pixels()
+        .map(|&Rgba([r, g, b, alpha])| {
+            Color::Rgba(Rgb888::new(r, g, b), alpha).blend_over(background)
+        })
+        .collect();
+    Ok((pixels, size))
+}
+
+/// Convert an error from encoding an image into a `TerminalError`.
+pub(crate) fn image_error(error: ImageError) -> TerminalError {
+    match error {
+        ImageError::IoError(error) => TerminalError::Io(error),
+        error => TerminalError::Io(io::Error::other(error)),
+    }
+}
+
+/// The shortest time a frame of a GIF is shown for, to stop fast animations
+/// from producing huge files.
+///
+/// Many viewers don't handle delays shorter than this properly anyway.
+const MIN_FRAME_TIME: Duration = Duration::from_millis(20);
+
+/// How long the last frame of a GIF is shown for, since there's no next frame
+/// to measure up to.
+const LAST_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// Records frames into an animated GIF, using the time between them as each
+/// frame's delay.
+pub(crate) struct GifRecorder {
+    encoder: GifEncoder<BufWriter<File>>,
+    /// The last frame captured, and when it was captured.
+    ///
+    /// This can't be encoded until the next frame comes along, since only then do
+    /// we know how long it's shown for.
+    last: Option<(RgbaImage, Instant)>,
+}
+
+impl GifRecorder {
+    pub(crate) fn new(path: impl AsRef<Path>) -> Result<Self, TerminalError> {
+        // NeuQuant's slowest speeds are far too slow to be recording frames as they
+        // happen.
+        let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), 10);
+        encoder.set_repeat(Repeat::Infinite).map_err(image_error)?;
+        Ok(Self {
+            encoder,
+            last: None,
+        })
+    }
+
+    /// Add a frame to the GIF.
+    pub(crate) fn capture(&mut self, image: RgbImage) -> Result<(), TerminalError> {
+        let now = Instant::now();
+        let image = DynamicImage::ImageRgb8(image).into_rgba8();
+        match self.last.take() {
+            // Frames which come too soon after the last one just replace it.
+            Some((_, time)) if now - time < MIN_FRAME_TIME => {
+                self.last = Some((image, time));
+            }
+            Some((last, time)) => {
+                self.encode(last, now - time)?;
+                self.last = Some((image, now));
+            }
+            None => self.last = Some((image, now)),
+        }
+        Ok(())
+    }
+
+    /// Finish writing the GIF.
+    pub(crate) fn finish(mut self) -> Result<(), TerminalError> {
+        if let Some((last, _)) = self.last.take() {
+            self.encode(last, LAST_FRAME_TIME)?;
+        }
+        // The GIF's trailer gets written when the encoder is dropped. There's no way to
+        // get the file back out to check that it worked, unfortunately.
+        drop(self.encoder);
+        Ok(())
+    }
+
+    fn encode(&mut self, image: RgbaImage, delay: Duration) -> Result<(), TerminalError> {
+        let frame = image::Frame::from_parts(image, 0, 0, Delay::from_saturating_duration(delay));
+        self.encoder.encode_frame(frame).map_err(image_error)
+    }
+}
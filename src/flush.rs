@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+#[cfg(feature = "sixel")]
+use embedded_graphics_core::pixelcolor::Rgb888;
+
+use crate::cell::TextCell;
+use crate::render::RenderConfig;
+use crate::writer::TerminalWriter;
+use crate::{cell_attributes, cell_changed, cell_contents, Attributes, Color};
+
+/// What the most recent [`TerminalDisplay::present`] (or
+/// [`TerminalDisplay::flush`]) did, from
+/// [`TerminalDisplay::last_present_stats`].
+///
+/// This is meant for working out why presenting is slow, or checking that a
+/// change in how something's drawn doesn't make it redraw more than it needs
+/// to.
+///
+/// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+/// [`TerminalDisplay::flush`]: crate::TerminalDisplay::flush
+/// [`TerminalDisplay::last_present_stats`]: crate::TerminalDisplay::last_present_stats
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PresentStats {
+    /// The number of cells which were checked for whether they'd changed,
+    /// which is all of the ones on screen.
+    pub cells_examined: usize,
+    /// The number of cells which were written to the terminal.
+    pub cells_redrawn: usize,
+    /// The number of bytes written to the terminal.
+    pub bytes_written: usize,
+    /// The number of times the foreground or background color was set.
+    /// Setting both of them at once counts as two.
+    pub color_changes: usize,
+    /// The number of times the cursor was moved explicitly, rather than just
+    /// moving along as cells were written.
+    pub cursor_moves: usize,
+}
+
+/// The parts of a display which decide what flushing it writes to the
+/// terminal.
+///
+/// These are borrowed separately from the display's writer, so that flushing
+/// can be measured by writing to something else instead.
+pub(crate) struct PendingFlush<'a> {
+    pub(crate) config: &'a RenderConfig,
+    pub(crate) buffer: &'a [Vec<Color>],
+    pub(crate) committed: &'a [Vec<Option<Color>>],
+    pub(crate) text: &'a HashMap<(u16, u16), TextCell>,
+    pub(crate) attributes: &'a HashMap<(u16, u16), Attributes>,
+    #[cfg(feature = "sixel")]
+    pub(crate) default_colors: (Rgb888, Rgb888),
+    pub(crate) origin: (u16, u16),
+    /// The number of (columns, rows) of cells which are both in the buffer
+    /// and on screen, which are the only ones that get drawn.
+    pub(crate) cells: (u16, u16),
+}
+
+impl PendingFlush<'_> {
+    /// Write everything which has changed since the last flush to `out`.
+    ///
+    /// Afterwards, every cell in `cells` is on screen as it is now. Returns
+    /// how many cells were written.
+    pub(crate) fn write(&self, out: &mut TerminalWriter<impl Write>) -> io::Result<usize> {
+        #[cfg(feature = "sixel")]
+        if !self.config.draws_cells() {
+            return self.write_sixel(out);
+        }
+
+        let (columns, rows) = self.cells;
+        let (origin_x, origin_y) = self.origin;
+        let pixel_count = self.config.render_mode.cell_pixel_count();
+        let mut redrawn = 0;
+        for row in 0..rows {
+            let mut column = 0;
+            while column < columns {
+                let attributes = cell_attributes(self.attributes, column, row);
+                if let Some(text) = self.text.get(&(column, row)) {
+                    if !text.drawn {
+                        self.write_text(out, column, row, text)?;
+                        redrawn += 1;
+                    }
+                    column += 1;
+                    continue;
+                }
+                if !cell_changed(self.config, self.buffer, self.committed, column, row) {
+                    column += 1;
+                    continue;
+                }
+
+                // Write runs of identical cells all at once.
+                let contents = cell_contents(self.config, self.buffer, column, row);
+                let mut end = column + 1;
+                while end < columns
+                    && !self.text.contains_key(&(end, row))
+                    && cell_changed(self.config, self.buffer, self.committed, end, row)
+                    && cell_attributes(self.attributes, end, row) == attributes
+                    && cell_contents(self.config, self.buffer, end, row) == contents
+                {
+                    end += 1;
+                }
+
+                out.start_update()?;
+                out.move_to(origin_x + column, origin_y + row)?;
+                out.set_attributes(attributes)?;
+                out.write_cells(self.config, &contents[..pixel_count], end - column)?;
+                redrawn += usize::from(end - column);
+                column = end;
+            }
+        }
+
+        self.finish(out)?;
+        Ok(redrawn)
+    }
+
+    /// Redraw the rows of cells which have changed as a sixel image, followed
+    /// by any text in them.
+    #[cfg(feature = "sixel")]
+    fn write_sixel(&self, out: &mut TerminalWriter<impl Write>) -> io::Result<usize> {
+        let (columns, rows) = self.cells;
+        let row_changed = |row: u16| {
+            (0..columns)
+                .any(|column| cell_changed(self.config, self.buffer, self.committed, column, row))
+        };
+        let changed = match (0..rows).find(|&row| row_changed(row)) {
+            Some(first) => {
+                let last = (first..rows)
+                    .rev()
+                    .find(|&row| row_changed(row))
+                    .unwrap_or(first);
+                first..last + 1
+            }
+            None => 0..0,
+        };
+        // The changed rows are redrawn all the way across.
+        let mut redrawn = changed.len() * usize::from(columns);
+        if !changed.is_empty() {
+            out.start_update()?;
+            crate::write_sixel_rows(
+                out,
+                self.config,
+                self.buffer,
+                self.default_colors,
+                self.origin,
+                columns,
+                changed.clone(),
+            )?;
+        }
+
+        for row in 0..rows {
+            for column in 0..columns {
+                match self.text.get(&(column, row)) {
+                    // The image has just been drawn over any text in the rows that changed.
+                    Some(text) if changed.contains(&row) => {
+                        self.write_text(out, column, row, text)?;
+                    }
+                    Some(text) if !text.drawn => {
+                        self.write_text(out, column, row, text)?;
+                        redrawn += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.finish(out)?;
+        Ok(redrawn)
+    }
+
+    /// Write the text in the given cell.
+    fn write_text(
+        &self,
+        out: &mut TerminalWriter<impl Write>,
+        column: u16,
+        row: u16,
+        text: &TextCell,
+    ) -> io::Result<()> {
+        let (origin_x, origin_y) = self.origin;
+        out.start_update()?;
+        out.move_to(origin_x + column, origin_y + row)?;
+        out.set_attributes(cell_attributes(self.attributes, column, row))?;
+        out.write_text(self.config, text.character, text.fg, text.bg)
+    }
+
+    /// Clean up after writing everything, and flush.
+    fn finish(&self, out: &mut TerminalWriter<impl Write>) -> io::Result<()> {
+        // Turn the attributes back off, so that they don't end up on anything else
+        // written to the terminal.
+        out.set_attributes(Attributes::NONE)?;
+        out.finish_update()?;
+        out.flush()
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::Write;
+
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+use crate::{Color, TerminalDisplay, TerminalError};
+
+/// A single frame being drawn to a [`TerminalDisplay`], created by
+/// [`TerminalDisplay::frame`].
+///
+/// Nothing drawn to the frame is shown until [`Frame::present`] is called, at
+/// which point everything that's changed gets drawn at once.
+///
+/// If the frame is dropped without being presented, it gets presented then
+/// instead, so that nothing drawn to it goes missing; but any errors from that
+/// are ignored, so it's best to call [`Frame::present`] explicitly.
+///
+/// [`TerminalDisplay`]: crate::TerminalDisplay
+/// [`TerminalDisplay::frame`]: crate::TerminalDisplay::frame
+/// [`Frame::present`]: crate::Frame::present
+#[must_use = "nothing drawn to a frame is shown until it's presented"]
+pub struct Frame<'a, W: Write> {
+    display: &'a mut TerminalDisplay<W>,
+    /// Whether the frame's been presented explicitly, so it doesn't need to be
+    /// presented when it's dropped.
+    presented: bool,
+}
+
+impl<'a, W: Write> Frame<'a, W> {
+    pub(crate) fn new(display: &'a mut TerminalDisplay<W>) -> Self {
+        Self {
+            display,
+            presented: false,
+        }
+    }
+
+    /// Show everything that's been drawn to the frame.
+    ///
+    /// This is the same as calling [`TerminalDisplay::present`].
+    ///
+    /// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+    pub fn present(mut self) -> Result<(), TerminalError> {
+        self.presented = true;
+        self.display.present()
+    }
+}
+
+impl<W: Write> Drop for Frame<'_, W> {
+    fn drop(&mut self) {
+        if !self.presented {
+            // We can't do anything about errors here; anyone who cares about them should
+            // have presented the frame themselves.
+            let _ = self.display.present();
+        }
+    }
+}
+
+impl<W: Write> OriginDimensions for Frame<'_, W> {
+    fn size(&self) -> Size {
+        self.display.size()
+    }
+}
+
+impl<W: Write> DrawTarget for Frame<'_, W> {
+    type Color = Color;
+    type Error = TerminalError;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), TerminalError>
+    where
+        I: IntoIterator<Item = Pixel<Color>>,
+    {
+        self.display.draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), TerminalError>
+    where
+        I: IntoIterator<Item = Color>,
+    {
+        self.display.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Color) -> Result<(), TerminalError> {
+        self.display.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), TerminalError> {
+        self.display.clear(color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics_core::prelude::*;
+
+    use crate::test_util;
+    use crate::{Color, TerminalDisplay};
+
+    #[test]
+    fn dropped_frame_presents() {
+        let (mut display, out) = test_util::display(TerminalDisplay::builder(), 1, 1);
+        {
+            let mut frame = display.frame();
+            Pixel(Point::new(0, 0), Color::Red)
+                .draw(&mut frame)
+                .unwrap();
+            assert_eq!(out.take(), "");
+        }
+        assert_eq!(out.take(), "\x1b[1;1H\x1b[38;5;9m\x1b[49m▀\x1b[1;1H");
+    }
+}
@@ -1,342 +1,3433 @@
+use std::collections::HashMap;
+use std::fs::File;
 use std::io::{self, BufWriter, Stdout, Write};
 use std::ops::Range;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crossterm::style::Color as CrosstermColor;
-use crossterm::{cursor, style, terminal, QueueableCommand};
+#[cfg(feature = "input")]
+use crossterm::event::Event;
+#[cfg(any(feature = "image", feature = "sixel"))]
+use embedded_graphics_core::pixelcolor::Rgb888;
 use embedded_graphics_core::prelude::*;
 use embedded_graphics_core::primitives::Rectangle;
 
+#[cfg(feature = "input")]
+mod animation;
+mod builder;
+mod cell;
 mod color;
+mod error;
+#[cfg(feature = "image")]
+mod export;
+mod flush;
+mod frame;
+mod lock;
+mod pacing;
+mod quantize;
+mod recorder;
+mod render;
+mod scale;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "sixel")]
+mod sixel;
+mod snapshot;
+mod tee;
+#[cfg(test)]
+mod test_util;
+mod tty;
+mod writer;
 
-pub use color::Color;
+#[cfg(feature = "input")]
+pub use animation::{run, Animation};
+pub use builder::TerminalDisplayBuilder;
+use cell::TextCell;
+pub use cell::{Attributes, CellUpdate};
+pub use color::{BlendMode, Color, ParseColorError};
+/// crossterm's event types, for use with [`TerminalDisplay::poll_event`].
+///
+/// [`TerminalDisplay::poll_event`]: crate::TerminalDisplay::poll_event
+#[cfg(feature = "input")]
+pub use crossterm::event;
+pub use error::TerminalError;
+use flush::PendingFlush;
+pub use flush::PresentStats;
+pub use frame::Frame;
+pub use lock::DisplayLock;
+pub use pacing::FrameStats;
+use pacing::OutputRate;
+use quantize::FloydSteinberg;
+pub use quantize::{ColorMode, Dithering};
+pub use recorder::Recorder;
+pub use render::{Backend, GlyphSet, Orientation, RenderMode};
+use render::{Levels, RenderConfig};
+pub use scale::{Fit, Sampling};
+pub use snapshot::BufferSnapshot;
+pub use tee::Tee;
+use tty::SizeSource;
+use writer::{ByteCount, TerminalWriter, WriteCounts};
+
+/// The most pixels any render mode puts in a single cell.
+const MAX_CELL_PIXELS: usize = 8;
+
+/// Get the size of the terminal in pixels from its size in rows/columns.
+fn size(config: &RenderConfig, width: u16, height: u16) -> Size {
+    let cell_size = config.cell_size();
+    let height = match config.backend {
+        Backend::Cells => height,
+        // Drawing a sixel image in the bottom row makes most terminals scroll.
+        #[cfg(feature = "sixel")]
+        Backend::Sixel { .. } => height.saturating_sub(1),
+    };
+    Size::new(
+        u32::from(width) * cell_size.width,
+        u32::from(height) * cell_size.height,
+    )
+}
+
+/// Get the size of `buffer` as (width, height).
+fn buffer_size<T>(buffer: &[Vec<T>]) -> (usize, usize) {
+    (buffer.first().map_or(0, |row| row.len()), buffer.len())
+}
+
+/// Get the number of cells needed to cover `buffer` as (columns, rows).
+fn buffer_cells<T>(config: &RenderConfig, buffer: &[Vec<T>]) -> (u16, u16) {
+    let (width, height) = buffer_size(buffer);
+    cells(config, Size::new(width as u32, height as u32))
+}
+
+/// Get the number of cells needed to cover an area of the given size in pixels
+/// as (columns, rows).
+fn cells(config: &RenderConfig, size: Size) -> (u16, u16) {
+    let cell_size = config.cell_size();
+    // A fixed-size buffer might need more cells than fit in a `u16`, but there's no
+    // way a terminal could show that many anyway.
+    (
+        size.width.div_ceil(cell_size.width).min(u16::MAX.into()) as u16,
+        size.height.div_ceil(cell_size.height).min(u16::MAX.into()) as u16,
+    )
+}
+
+/// Get the ranges of pixel columns and rows in a buffer of the given size which
+/// make up the cell at the given column and row.
+///
+/// These get cut off at the edges of the buffer, so they can be smaller than a
+/// whole cell. If the display is flipped, these are the pixels which get
+/// flipped into the cell, in which case they're drawn in the reverse order.
+fn cell_pixels(
+    config: &RenderConfig,
+    (width, height): (usize, usize),
+    column: u16,
+    row: u16,
+) -> (Range<usize>, Range<usize>) {
+    // Cells are at most a few pixels across, so these can't overflow.
+    let cell_width = config.cell_size().width as usize;
+    let cell_height = config.cell_size().height as usize;
+    let x = usize::from(column) * cell_width;
+    let y = usize::from(row) * cell_height;
+    let xs = x.min(width)..(x + cell_width).min(width);
+    let ys = y.min(height)..(y + cell_height).min(height);
+    let flip = |range: Range<usize>, len: usize, flip: bool| {
+        if flip {
+            len - range.end..len - range.start
+        } else {
+            range
+        }
+    };
+    (
+        flip(xs, width, config.orientation.flip_x()),
+        flip(ys, height, config.orientation.flip_y()),
+    )
+}
+
+/// What to do with the cursor after presenting a frame.
+#[derive(Copy, Clone, Debug)]
+enum CursorPark {
+    /// Move it to the bottom-left corner of the terminal.
+    BottomLeft,
+    /// Move it to the given (column, row) of the terminal.
+    At(u16, u16),
+    /// Hide it.
+    Hidden,
+}
+
+impl CursorPark {
+    /// Move the cursor out of the way like this, on a terminal of the given
+    /// size.
+    fn park(self, out: &mut TerminalWriter<impl Write>, (_, height): (u16, u16)) -> io::Result<()> {
+        match self {
+            CursorPark::BottomLeft => out.move_to(0, height.saturating_sub(1)),
+            CursorPark::At(column, row) => out.move_to(column, row),
+            // This gets undone when restoring the terminal like any other time the cursor's
+            // hidden.
+            CursorPark::Hidden => out.set_cursor_hidden(true),
+        }
+    }
+}
+
+/// An implementation of `embedded_graphics::DrawTarget` for the terminal using
+/// crossterm.
+///
+/// By default, a pixel is half of a character in the terminal, since they're
+/// usually about 1x2; other ways of splitting up characters into pixels can be
+/// chosen with [`TerminalDisplay::with_render_mode`].
+///
+/// Drawing only updates an in-memory buffer; to show the rendered image, call
+/// [`TerminalDisplay::present`] (or [`TerminalDisplay::flush`]). Only the
+/// cells which have changed since the last time get redrawn.
+///
+/// By default, output goes to stdout; any other writer can be used instead with
+/// [`TerminalDisplay::with_writer`]. Output is buffered, so writing to stdout
+/// directly (e.g. with `crossterm::execute!`) bypasses that buffer and can end
+/// up out of order with what the display writes; use the display's own
+/// methods like [`TerminalDisplay::set_cursor_visible`] instead where
+/// possible.
+///
+/// The display is [`Send`] as long as its writer is, so it can be moved to a
+/// dedicated thread for rendering. It isn't [`Sync`], but nothing can be done
+/// through a shared reference which would make that useful anyway.
+///
+/// When the display is dropped, it flushes anything that hasn't been shown yet,
+/// and then tries to put the terminal back the way it was, showing the cursor
+/// and resetting the colors if they've been changed. Any errors while doing so
+/// are ignored; call [`TerminalDisplay::flush`] and
+/// [`TerminalDisplay::restore`] first to handle them.
+///
+/// [`TerminalDisplay::with_render_mode`]: crate::TerminalDisplay::with_render_mode
+/// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+/// [`TerminalDisplay::flush`]: crate::TerminalDisplay::flush
+/// [`TerminalDisplay::with_writer`]: crate::TerminalDisplay::with_writer
+/// [`TerminalDisplay::set_cursor_visible`]: crate::TerminalDisplay::set_cursor_visible
+/// [`TerminalDisplay::restore`]: crate::TerminalDisplay::restore
+pub struct TerminalDisplay<W: Write = BufWriter<Stdout>> {
+    /// The color of every pixel, as a list of rows.
+    ///
+    /// This is needed because it's impossible to get back the color of a cell,
+    /// and we need to preserve the colors of the rest of the cell when writing
+    /// a single pixel.
+    buffer: Vec<Vec<Color>>,
+    /// The color of every pixel as of the last flush, so that we only need to
+    /// redraw the cells that have changed.
+    ///
+    /// `None` means we don't know what's currently on screen for that pixel,
+    /// and so its cell has to be redrawn regardless.
+    committed: Vec<Vec<Option<Color>>>,
+    /// The color of every pixel the display's ever had, as of the last time it
+    /// was resized, as a list of rows.
+    ///
+    /// This is as big as the display's ever been, so that whatever gets cut
+    /// off when the terminal shrinks comes back if it grows again.
+    retained: Vec<Vec<Color>>,
+    /// The cells which have had text written over them, by (column, row).
+    text: HashMap<(u16, u16), TextCell>,
+    /// The text attributes of every cell which has any, by (column, row).
+    attributes: HashMap<(u16, u16), Attributes>,
+    /// The size of the terminal as (width, height) in cells, as of the last
+    /// time we checked.
+    terminal_size: (u16, u16),
+    /// Where to check the size of the terminal.
+    size_source: SizeSource,
+    /// Whether to check the size of the terminal every time something's drawn,
+    /// rather than only after [`TerminalDisplay::notify_resize`] is called.
+    ///
+    /// [`TerminalDisplay::notify_resize`]: crate::TerminalDisplay::notify_resize
+    poll_size: bool,
+    /// Whether we've been told the terminal has been resized since we last
+    /// checked its size.
+    resize_pending: bool,
+    /// A function to call whenever the terminal is resized.
+    on_resize: Option<Box<dyn FnMut(u16, u16) + Send>>,
+    /// The size of the display in pixels, if it's been fixed rather than
+    /// following the size of the terminal.
+    fixed_size: Option<Size>,
+    /// The column and row of the terminal where the top-left corner of the
+    /// display is drawn.
+    origin: (u16, u16),
+    /// The point in drawing coordinates which appears at the top-left corner of
+    /// the display.
+    offset: Point,
+    /// Whether drawing outside the display is an error rather than being
+    /// ignored.
+    strict: bool,
+    /// Whether to flush after everything that's drawn, rather than waiting to
+    /// be flushed explicitly.
+    auto_flush: bool,
+    /// The stack of areas drawing is clipped to, in drawing coordinates.
+    clip: Vec<Rectangle>,
+    /// When the last frame was presented, or skipped by
+    /// [`TerminalDisplay::present_at_most`].
+    last_present: Option<Instant>,
+    /// How fast the output has been taking frames, if
+    /// [`TerminalDisplay::present_at_most`] is skipping frames it can't keep
+    /// up with.
+    output_rate: Option<OutputRate>,
+    frame_stats: FrameStats,
+    present_stats: PresentStats,
+    /// The cells which have been drawn to since the damage was last taken with
+    /// [`TerminalDisplay::take_damage`].
+    damage: Option<Rectangle>,
+    /// The smallest size the terminal can be as (columns, rows) before a
+    /// message saying it's too small gets shown instead of the display.
+    min_size: Option<(u16, u16)>,
+    /// The size of the terminal when that message was last shown, if it's
+    /// still being shown.
+    too_small_shown: Option<(u16, u16)>,
+    cursor_park: CursorPark,
+    config: RenderConfig,
+    dithering: Dithering,
+    sampling: Sampling,
+    blend_mode: BlendMode,
+    /// The state of the last dithering pass, which is kept around so that its
+    /// memory can be reused rather than allocated for every image.
+    dither_scratch: FloydSteinberg,
+    /// The pixels of the last image scaled by
+    /// [`TerminalDisplay::blit_scaled`], kept around for the same reason.
+    scale_scratch: Vec<Color>,
+    /// The RGB values to use for the terminal's default (background,
+    /// foreground) colors when exporting images or drawing sixels.
+    #[cfg(any(feature = "image", feature = "sixel"))]
+    default_colors: (Rgb888, Rgb888),
+    /// The GIF every presented frame is being recorded to, if any.
+    #[cfg(feature = "image")]
+    gif: Option<export::GifRecorder>,
+    /// We need to store this between runs so that
+    stdout: TerminalWriter<W>,
+}
+
+impl TerminalDisplay {
+    pub fn new() -> Result<Self, TerminalError> {
+        Self::builder().build()
+    }
+
+    /// Start configuring a display.
+    pub fn builder() -> TerminalDisplayBuilder {
+        TerminalDisplayBuilder::default()
+    }
+
+    /// Create a display which takes over the whole terminal, by switching to
+    /// the alternate screen and enabling raw mode.
+    ///
+    /// Both are undone by [`TerminalDisplay::restore`], or when the display is
+    /// dropped. That includes when it's dropped while unwinding from a panic,
+    /// so the user's shell doesn't get left in a broken state.
+    ///
+    /// [`TerminalDisplay::restore`]: crate::TerminalDisplay::restore
+    pub fn fullscreen() -> Result<Self, TerminalError> {
+        Self::builder()
+            .alternate_screen(true)
+            .raw_mode(true)
+            .build()
+    }
+
+    /// Create a display which is always `width` by `height` pixels, regardless
+    /// of the size of the terminal.
+    ///
+    /// If the terminal is too small to fit the whole display, only the part
+    /// which fits in the top-left corner is shown.
+    pub fn with_fixed_size(width: u32, height: u32) -> Result<Self, TerminalError> {
+        Self::builder().fixed_size(width, height).build()
+    }
+
+    /// Create a display which only takes up `region` of the terminal, where
+    /// `region` is measured in cells rather than pixels.
+    ///
+    /// This lets the display share the terminal with other things, like a
+    /// status bar or another display. Like with
+    /// [`TerminalDisplay::with_fixed_size`], the display stays the same size
+    /// even if the terminal is resized, and only the part which fits on the
+    /// terminal is shown.
+    ///
+    /// [`TerminalDisplay::with_fixed_size`]: crate::TerminalDisplay::with_fixed_size
+    pub fn with_region(region: Rectangle) -> Result<Self, TerminalError> {
+        Self::builder().region(region).build()
+    }
+
+    /// Lock stdout until the returned [`DisplayLock`] is dropped, so that
+    /// nothing else can write to it in the middle of what the display draws.
+    ///
+    /// By default, the display writes to stdout through a [`BufWriter`], which
+    /// only locks stdout for as long as it takes to write out its buffer each
+    /// time it fills up or gets flushed. A big frame can take more than one of
+    /// those, and other threads writing to stdout in between would end up in
+    /// the middle of the frame, messing up everything drawn after them. Holding
+    /// the lock across drawing and presenting a frame prevents that.
+    ///
+    /// This doesn't stop the thread holding the lock from writing to stdout
+    /// itself, since stdout's lock can be locked again by the same thread. To
+    /// hold the lock the whole time the display exists, create it with
+    /// `TerminalDisplay::with_writer(io::stdout().lock())` instead.
+    ///
+    /// [`DisplayLock`]: crate::DisplayLock
+    pub fn lock(&mut self) -> DisplayLock<'_> {
+        DisplayLock::new(self)
+    }
+
+    /// Create a display which draws to the terminal device `tty`, like
+    /// `/dev/tty`, rather than to stdout.
+    ///
+    /// Unlike [`TerminalDisplay::with_writer`], the size of the display is
+    /// taken from that terminal rather than the process's controlling
+    /// terminal, which can be different; so this works even when stdout is
+    /// redirected somewhere else, like a pipe. That's only possible on Unix,
+    /// though: elsewhere, the size is still taken from the controlling
+    /// terminal.
+    ///
+    /// [`TerminalDisplay::with_writer`]: crate::TerminalDisplay::with_writer
+    pub fn for_tty(tty: File) -> Result<TerminalDisplay<BufWriter<File>>, TerminalError> {
+        Self::builder().build_for_tty(tty)
+    }
+
+    /// Create a display which records everything it draws to an asciicast
+    /// file at `path`, as well as drawing it to stdout.
+    ///
+    /// See [`Recorder`] for more details.
+    ///
+    /// [`Recorder`]: crate::Recorder
+    pub fn with_recording(
+        path: impl AsRef<Path>,
+    ) -> Result<TerminalDisplay<Recorder<BufWriter<Stdout>>>, TerminalError> {
+        let recorder = Recorder::new(BufWriter::new(io::stdout()), path)?;
+        Self::builder().build_with_writer(recorder)
+    }
+
+    /// Create a display which splits up cells into pixels using the given
+    /// render mode.
+    pub fn with_render_mode(render_mode: RenderMode) -> Result<Self, TerminalError> {
+        Self::builder().render_mode(render_mode).build()
+    }
+
+    /// Create a display which only uses colors available in the given color
+    /// mode.
+    pub fn with_color_mode(color_mode: ColorMode) -> Result<Self, TerminalError> {
+        Self::builder().color_mode(color_mode).build()
+    }
+
+    /// Create a display which draws cells using the given set of characters.
+    pub fn with_glyph_set(glyph_set: GlyphSet) -> Result<Self, TerminalError> {
+        Self::builder().glyph_set(glyph_set).build()
+    }
+}
+
+impl<W: Write> TerminalDisplay<W> {
+    /// Create a display which writes its output to `writer` rather than to
+    /// stdout.
+    ///
+    /// The size of the display is still taken from the terminal, so `writer`
+    /// should end up writing to that terminal. To write to somewhere else as
+    /// well, like a log file, wrap both in a [`Tee`].
+    ///
+    /// [`Tee`]: crate::Tee
+    pub fn with_writer(writer: W) -> Result<Self, TerminalError> {
+        TerminalDisplay::builder().build_with_writer(writer)
+    }
+
+    fn with_config(
+        writer: W,
+        mut config: RenderConfig,
+        fixed_size: Option<Size>,
+        size_source: SizeSource,
+    ) -> Result<Self, TerminalError> {
+        config.color_mode = config.color_mode.resolve();
+        let terminal_size = size_source.size()?;
+        let size = fixed_size.unwrap_or_else(|| size(&config, terminal_size.0, terminal_size.1));
+        let (width, height) = (size.width as usize, size.height as usize);
+        // Assume the screen starts out blank, so that we don't overwrite anything we
+        // haven't drawn over; unless empty cells are drawn as something else, in which
+        // case they all need drawing.
+        let blank = match config.empty_cell {
+            Some(_) => None,
+            None => Some(Color::BgColor),
+        };
+        Ok(Self {
+            buffer: vec![vec![Color::BgColor; width]; height],
+            committed: vec![vec![blank; width]; height],
+            retained: Vec::new(),
+            text: HashMap::new(),
+            attributes: HashMap::new(),
+            terminal_size,
+            size_source,
+            poll_size: true,
+            resize_pending: false,
+            on_resize: None,
+            fixed_size,
+            origin: (0, 0),
+            offset: Point::zero(),
+            strict: false,
+            auto_flush: false,
+            clip: Vec::new(),
+            last_present: None,
+            output_rate: None,
+            frame_stats: FrameStats::default(),
+            present_stats: PresentStats::default(),
+            damage: None,
+            min_size: None,
+            too_small_shown: None,
+            cursor_park: CursorPark::BottomLeft,
+            config,
+            dithering: Dithering::default(),
+            sampling: Sampling::default(),
+            blend_mode: BlendMode::default(),
+            dither_scratch: FloydSteinberg::default(),
+            scale_scratch: Vec::new(),
+            #[cfg(any(feature = "image", feature = "sixel"))]
+            default_colors: (Rgb888::BLACK, Rgb888::WHITE),
+            #[cfg(feature = "image")]
+            gif: None,
+            stdout: TerminalWriter::new(writer),
+        })
+    }
+
+    /// Start drawing a new frame, which is shown all at once when
+    /// [`Frame::present`] is called.
+    ///
+    /// [`Frame::present`]: crate::Frame::present
+    pub fn frame(&mut self) -> Frame<'_, W> {
+        Frame::new(self)
+    }
+
+    /// Show everything that's been drawn so far.
+    ///
+    /// This is the same as [`TerminalDisplay::flush`], except that the cursor
+    /// is then moved out of the way so that it isn't left in the middle of the
+    /// image; by default, to the bottom-left corner of the terminal, but that
+    /// can be changed with [`TerminalDisplay::set_cursor_park`].
+    ///
+    /// [`TerminalDisplay::flush`]: crate::TerminalDisplay::flush
+    /// [`TerminalDisplay::set_cursor_park`]: crate::TerminalDisplay::set_cursor_park
+    pub fn present(&mut self) -> Result<(), TerminalError> {
+        // Start timing the frame from here, so that the time spent presenting it
+        // counts towards it in `present_at_most`.
+        self.last_present = Some(Instant::now());
+        if self.stdout.suspended() {
+            self.present_stats = PresentStats::default();
+            return Ok(());
+        }
+        let start = self.stdout.counts();
+        self.flush()?;
+        self.cursor_park
+            .park(&mut self.stdout, self.terminal_size)?;
+        self.stdout.flush()?;
+        // Include parking the cursor in what the flush wrote.
+        let PresentStats {
+            cells_examined,
+            cells_redrawn,
+            ..
+        } = self.present_stats;
+        self.record_present_stats(start, cells_examined, cells_redrawn);
+
+        #[cfg(feature = "image")]
+        if let Some(gif) = &mut self.gif {
+            gif.capture(export::to_image(
+                &self.buffer,
+                &self.config,
+                self.default_colors,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Show everything that's been drawn so far, like
+    /// [`TerminalDisplay::present`], but first sleep for as long as it takes to
+    /// keep the frame rate below `fps` frames per second.
+    ///
+    /// This is meant to be called once per iteration of a drawing loop, so that
+    /// the loop doesn't use more CPU than it needs to. It returns whether
+    /// anything was presented, which isn't the case if `fps` is 0, or if the
+    /// frame was skipped by [`TerminalDisplay::set_adaptive_frame_rate`].
+    ///
+    /// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+    /// [`TerminalDisplay::set_adaptive_frame_rate`]: crate::TerminalDisplay::set_adaptive_frame_rate
+    pub fn present_at_most(&mut self, fps: u32) -> Result<bool, TerminalError> {
+        if fps == 0 {
+            return Ok(false);
+        }
+
+        let frame_time = Duration::from_secs(1) / fps;
+        if let Some(last_present) = self.last_present {
+            if let Some(remaining) = frame_time.checked_sub(last_present.elapsed()) {
+                thread::sleep(remaining);
+            }
+        }
+
+        let Some(rate) = &self.output_rate else {
+            self.present()?;
+            self.frame_stats.drawn += 1;
+            return Ok(true);
+        };
+        let bytes = self.pending_bytes();
+        if !rate.keeps_up(bytes) {
+            // This still counts as a frame, so that the loop carries on at the same pace
+            // rather than spinning until the output catches up.
+            self.last_present = Some(Instant::now());
+            self.frame_stats.skipped += 1;
+            return Ok(false);
+        }
+        let start = Instant::now();
+        self.present()?;
+        if let Some(rate) = &mut self.output_rate {
+            rate.record(bytes, start);
+        }
+        self.frame_stats.drawn += 1;
+        Ok(true)
+    }
+
+    /// Set whether [`TerminalDisplay::present_at_most`] skips frames when the
+    /// terminal can't take them as fast as they're being drawn, like over a
+    /// slow SSH connection. This is off by default.
+    ///
+    /// While it's on, the display keeps an estimate of how fast the terminal
+    /// has been taking what's written to it, and a frame gets skipped if
+    /// writing it (see [`TerminalDisplay::pending_bytes`]) would take longer
+    /// than it's been since the last frame was presented. Nothing drawn is
+    /// lost: it's just presented along with the next frame that isn't
+    /// skipped. This stops the drawing loop from falling further and further
+    /// behind what's on screen.
+    ///
+    /// [`TerminalDisplay::present_at_most`]: crate::TerminalDisplay::present_at_most
+    /// [`TerminalDisplay::pending_bytes`]: crate::TerminalDisplay::pending_bytes
+    pub fn set_adaptive_frame_rate(&mut self, adaptive: bool) {
+        if adaptive != self.output_rate.is_some() {
+            self.output_rate = adaptive.then(OutputRate::default);
+        }
+    }
+
+    /// Get how many frames [`TerminalDisplay::present_at_most`] has presented
+    /// and skipped so far.
+    ///
+    /// [`TerminalDisplay::present_at_most`]: crate::TerminalDisplay::present_at_most
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// Get what the most recent [`TerminalDisplay::present`] or
+    /// [`TerminalDisplay::flush`] did: how many cells it looked at and
+    /// redrew, and what it wrote to the terminal to do so.
+    ///
+    /// These are always kept track of, since it only takes a few additions.
+    ///
+    /// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+    /// [`TerminalDisplay::flush`]: crate::TerminalDisplay::flush
+    pub fn last_present_stats(&self) -> PresentStats {
+        self.present_stats
+    }
+
+    /// Set the stats for the last present to what's been written since `start`,
+    /// along with the number of cells it examined and redrew.
+    fn record_present_stats(
+        &mut self,
+        start: WriteCounts,
+        cells_examined: usize,
+        cells_redrawn: usize,
+    ) {
+        let written = self.stdout.counts().since(start);
+        self.present_stats = PresentStats {
+            cells_examined,
+            cells_redrawn,
+            bytes_written: written.bytes,
+            color_changes: written.color_changes,
+            cursor_moves: written.cursor_moves,
+        };
+    }
+
+    /// Check that the terminal is at least `min_columns` by `min_rows` cells,
+    /// returning [`TerminalError::TerminalTooSmall`] if it isn't.
+    ///
+    /// This checks the terminal's current size, so it's worth checking again
+    /// after the terminal's been resized. To have the display deal with that
+    /// by itself, see [`TerminalDisplay::set_min_size`].
+    ///
+    /// [`TerminalError::TerminalTooSmall`]: crate::TerminalError::TerminalTooSmall
+    /// [`TerminalDisplay::set_min_size`]: crate::TerminalDisplay::set_min_size
+    pub fn require_size(&self, min_columns: u16, min_rows: u16) -> Result<(), TerminalError> {
+        let have = self.size_source.size()?;
+        if have.0 < min_columns || have.1 < min_rows {
+            return Err(TerminalError::TerminalTooSmall {
+                have,
+                need: (min_columns, min_rows),
+            });
+        }
+        Ok(())
+    }
+
+    /// Set the smallest size the terminal can be as (columns, rows), or `None`
+    /// for there not to be one, which is the default.
+    ///
+    /// Whenever the terminal is flushed while it's smaller than that, the
+    /// terminal is cleared and a message saying it's too small is shown in
+    /// the middle of it instead of the display. Drawing still works as usual
+    /// in the meantime, and as soon as the terminal's been made big enough
+    /// again, the message is cleared away and everything is drawn again.
+    pub fn set_min_size(&mut self, min_size: Option<(u16, u16)>) {
+        self.min_size = min_size;
+    }
+
+    /// Write all the cells which have changed since the last flush to the
+    /// terminal.
+    pub fn flush(&mut self) -> Result<(), TerminalError> {
+        let start = self.stdout.counts();
+        let (width, height) = self.resize()?;
+        // Everything gets redrawn when the display is resumed anyway.
+        if self.stdout.suspended() {
+            self.present_stats = PresentStats::default();
+            return Ok(());
+        }
+        if let Some(need) = self
+            .min_size
+            .filter(|&(columns, rows)| width < columns || height < rows)
+        {
+            if self.too_small_shown != Some((width, height)) {
+                self.stdout.reset();
+                write_too_small(&mut self.stdout, &self.config, (width, height), need)?;
+                self.too_small_shown = Some((width, height));
+            }
+            self.record_present_stats(start, 0, 0);
+            return Ok(());
+        }
+        if self.too_small_shown.take().is_some() {
+            // Get rid of the message, and then draw everything over again.
+            self.stdout.clear_screen()?;
+            self.invalidate();
+        }
+
+        // Something else might have changed the colors or moved the cursor since we
+        // last flushed.
+        self.stdout.reset();
+
+        let cells = self.visible_cells(width, height);
+        let pending = PendingFlush {
+            config: &self.config,
+            buffer: &self.buffer,
+            committed: &self.committed,
+            text: &self.text,
+            attributes: &self.attributes,
+            #[cfg(feature = "sixel")]
+            default_colors: self.default_colors,
+            origin: self.origin,
+            cells,
+        };
+        let redrawn = pending.write(&mut self.stdout)?;
+
+        // Everything that's on screen is now up to date.
+        let (columns, rows) = cells;
+        for row in 0..rows {
+            for column in 0..columns {
+                match self.text.get_mut(&(column, row)) {
+                    Some(text) => text.drawn = true,
+                    None => self.commit_cell(column, row),
+                }
+            }
+        }
+        let examined = usize::from(columns) * usize::from(rows);
+        self.record_present_stats(start, examined, redrawn);
+        Ok(())
+    }
+
+    /// Work out how many bytes [`TerminalDisplay::present`] would write right
+    /// now, without writing anything.
+    ///
+    /// This takes into account everything the display knows about the
+    /// terminal's state, like which cells are already up to date and what
+    /// colors it's already set to, so it's exactly what presenting writes
+    /// unless the terminal gets resized first. That makes it useful for
+    /// profiling, or for skipping frames when the output can't keep up.
+    ///
+    /// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+    pub fn pending_bytes(&self) -> usize {
+        if self.stdout.suspended() {
+            return 0;
+        }
+        let mut out = self.stdout.fork(ByteCount::default());
+        out.reset();
+        let (width, height) = self.terminal_size;
+        let pending = PendingFlush {
+            config: &self.config,
+            buffer: &self.buffer,
+            committed: &self.committed,
+            text: &self.text,
+            attributes: &self.attributes,
+            #[cfg(feature = "sixel")]
+            default_colors: self.default_colors,
+            origin: self.origin,
+            cells: self.visible_cells(width, height),
+        };
+        // Counting bytes can't fail.
+        pending.write(&mut out).unwrap();
+        self.cursor_park.park(&mut out, self.terminal_size).unwrap();
+        out.take_writer().0
+    }
+
+    /// Get the number of (columns, rows) of cells which are both in the buffer
+    /// and on screen, given the size of the terminal.
+    fn visible_cells(&self, width: u16, height: u16) -> (u16, u16) {
+        let (columns, rows) = buffer_cells(&self.config, &self.buffer);
+        let (origin_x, origin_y) = self.origin;
+        let height = match self.config.backend {
+            Backend::Cells => height,
+            // The bottom row of the terminal is left alone for the same reason as it's
+            // left out of the size of the display.
+            #[cfg(feature = "sixel")]
+            Backend::Sixel { .. } => height.saturating_sub(1),
+        };
+        (
+            columns.min(width.saturating_sub(origin_x)),
+            rows.min(height.saturating_sub(origin_y)),
+        )
+    }
+
+    /// Get all the cells which have changed since the last flush, for drawing
+    /// them some other way than writing to a terminal.
+    ///
+    /// This includes cells which don't fit on the terminal. Flushing (or
+    /// presenting) marks the cells which were drawn as unchanged again, which
+    /// doesn't include those. Cells covered by text from
+    /// [`TerminalDisplay::put_str`] are left out, since they aren't drawn from
+    /// pixels.
+    ///
+    /// This is always empty if the display isn't drawn with
+    /// [`Backend::Cells`].
+    ///
+    /// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+    /// [`Backend::Cells`]: crate::Backend::Cells
+    pub fn dirty_cells(&self) -> impl Iterator<Item = CellUpdate> + '_ {
+        let (columns, rows) = if self.config.draws_cells() {
+            buffer_cells(&self.config, &self.buffer)
+        } else {
+            (0, 0)
+        };
+        (0..rows)
+            .flat_map(move |row| (0..columns).map(move |column| (column, row)))
+            .filter(|&(column, row)| {
+                !self.text.contains_key(&(column, row))
+                    && cell_changed(&self.config, &self.buffer, &self.committed, column, row)
+            })
+            .map(|(column, row)| CellUpdate {
+                column,
+                row,
+                render_mode: self.config.render_mode,
+                pixels: cell_contents(&self.config, &self.buffer, column, row),
+                attributes: cell_attributes(&self.attributes, column, row),
+            })
+    }
+
+    /// Mark every cell as needing to be redrawn on the next flush, even if it
+    /// hasn't changed.
+    ///
+    /// Only the cells which have changed normally get redrawn, so this is
+    /// needed to repair the display if something else has written over the
+    /// terminal. Clearing the display does this automatically.
+    pub fn invalidate(&mut self) {
+        for row in &mut self.committed {
+            row.fill(None);
+        }
+        for text in self.text.values_mut() {
+            text.drawn = false;
+        }
+    }
+
+    /// Mark every cell which overlaps `area` as needing to be redrawn on the
+    /// next flush, like [`TerminalDisplay::invalidate`] but only for part of
+    /// the display.
+    ///
+    /// [`TerminalDisplay::invalidate`]: crate::TerminalDisplay::invalidate
+    pub fn invalidate_region(&mut self, area: &Rectangle) {
+        let (width, height) = buffer_size(&self.buffer);
+        let buffer_bounds = Rectangle::new(Point::zero(), Size::new(width as u32, height as u32));
+        let area = self.clamp_area(&buffer_bounds, area);
+        if area.is_zero_sized() {
+            return;
+        }
+
+        // We know these are positive because they have to be within the buffer.
+        let start_x = area.top_left.x as usize;
+        let end_x = start_x + area.size.width as usize;
+        let start_y = area.top_left.y as usize;
+        let end_y = start_y + area.size.height as usize;
+        for y in start_y..end_y {
+            self.committed[y][start_x..end_x].fill(None);
+        }
+
+        for (&(column, row), text) in &mut self.text {
+            let (xs, ys) = cell_pixels(&self.config, (width, height), column, row);
+            if xs.start < end_x && xs.end > start_x && ys.start < end_y && ys.end > start_y {
+                text.drawn = false;
+            }
+        }
+    }
+
+    /// Set which colors the terminal can display.
+    ///
+    /// With [`ColorMode::Auto`], this goes back to using the mode detected
+    /// from the environment.
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        let color_mode = color_mode.resolve();
+        if self.config.color_mode != color_mode {
+            self.config.color_mode = color_mode;
+            self.invalidate();
+        }
+    }
+
+    /// Only use the colors in `palette`, by switching to
+    /// [`ColorMode::Palette`].
+    ///
+    /// This accepts the built-in palettes as they are, e.g.
+    /// `display.set_palette(ColorMode::PICO_8)`. It's usually worth turning on
+    /// dithering too (see [`TerminalDisplay::set_dithering`]), since
+    /// palettes like these are small.
+    ///
+    /// [`TerminalDisplay::set_dithering`]: crate::TerminalDisplay::set_dithering
+    pub fn set_palette(&mut self, palette: impl Into<Vec<Color>>) {
+        self.set_color_mode(ColorMode::Palette(palette.into()));
+    }
+
+    /// Get the mode the display is using for which colors the terminal can
+    /// display.
+    ///
+    /// This is whichever mode was picked, unless that was [`ColorMode::Auto`],
+    /// in which case it's the mode that was detected.
+    pub fn detected_color_mode(&self) -> ColorMode {
+        self.config.color_mode.clone()
+    }
+
+    /// Set how the display is flipped or rotated when it's drawn to the
+    /// terminal, which is [`Orientation::Normal`] by default.
+    ///
+    /// This is useful for mirroring a physical display which is mounted
+    /// upside-down or seen in a mirror. Nothing else about the display
+    /// changes, so things get drawn to it exactly the same way.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        if self.config.orientation != orientation {
+            self.config.orientation = orientation;
+            self.invalidate();
+        }
+    }
+
+    /// Set whether to draw [`RenderMode::Sextant`] cells using quadrant
+    /// characters, for terminals whose fonts don't include the sextant
+    /// characters.
+    ///
+    /// This loses some detail, since the middle row of each cell gets drawn
+    /// over both the top and bottom halves.
+    pub fn set_sextant_fallback(&mut self, fallback: bool) {
+        if self.config.sextant_fallback != fallback {
+            self.config.sextant_fallback = fallback;
+            self.invalidate();
+        }
+    }
+
+    /// Set whether to pick which dots are on in [`RenderMode::Braille`] by how
+    /// bright each pixel is, for drawing photos and other images which don't
+    /// have a background.
+    ///
+    /// With `Some(threshold)`, a pixel's dot is on if its
+    /// [luminance](Color::luminance) is above `threshold`; 0.5 is a good place
+    /// to start. With `None`, which is the default, a pixel's dot is on unless
+    /// it's [`Color::BgColor`].
+    ///
+    /// This combines with [`Dithering::FloydSteinberg`]: images drawn with
+    /// [`DrawTarget::fill_contiguous`] are dithered to black and white first,
+    /// so that they keep their shading rather than just being cut off at the
+    /// threshold.
+    pub fn set_braille_threshold(&mut self, threshold: Option<f32>) {
+        if self.config.braille_threshold != threshold {
+            self.config.braille_threshold = threshold;
+            self.invalidate();
+        }
+    }
+
+    /// Set how bright RGB colors are displayed, as a factor which each of
+    /// their channels gets multiplied by when they're sent to the terminal;
+    /// for example, 0.5 halves them.
+    ///
+    /// This starts out as 1, which leaves them alone. Unlike
+    /// [`Color::scale`], it works on the values which get sent to the terminal
+    /// rather than in linear RGB, the same way a monitor's brightness
+    /// setting does. It's applied after the gamma set with
+    /// [`TerminalDisplay::set_gamma`], and can't make a channel any brighter
+    /// than the maximum.
+    ///
+    /// Only RGB colors are adjusted: named colors and [`Color::AnsiValue`]
+    /// are passed through unchanged, since the terminal decides what they
+    /// look like. That includes the terminal's default colors, unless a
+    /// canvas color has been set with
+    /// [`TerminalDisplayBuilder::canvas_color`]. What's in the buffer doesn't
+    /// change, so neither does [`TerminalDisplay::get_pixel`], but images
+    /// exported from the display are adjusted too.
+    ///
+    /// [`TerminalDisplay::set_gamma`]: crate::TerminalDisplay::set_gamma
+    /// [`TerminalDisplayBuilder::canvas_color`]: crate::TerminalDisplayBuilder::canvas_color
+    /// [`TerminalDisplay::get_pixel`]: crate::TerminalDisplay::get_pixel
+    pub fn set_brightness(&mut self, factor: f32) {
+        if self.config.levels.brightness != factor {
+            self.config.levels = Levels::new(factor, self.config.levels.gamma);
+            self.invalidate();
+        }
+    }
+
+    /// Set the gamma RGB colors are displayed with: each of their channels,
+    /// going from 0 to 1, is raised to the power of `1 / gamma` when they're
+    /// sent to the terminal.
+    ///
+    /// This starts out as 1, which leaves them alone. Gammas above 1 brighten
+    /// the midtones, and gammas below 1 darken them, without changing black
+    /// or white. Which colors are adjusted is the same as for
+    /// [`TerminalDisplay::set_brightness`].
+    ///
+    /// [`TerminalDisplay::set_brightness`]: crate::TerminalDisplay::set_brightness
+    pub fn set_gamma(&mut self, gamma: f32) {
+        if self.config.levels.gamma != gamma {
+            self.config.levels = Levels::new(self.config.levels.brightness, gamma);
+            self.invalidate();
+        }
+    }
+
+    /// Set whether to check the size of the terminal every time something's
+    /// drawn, which is the default.
+    ///
+    /// Checking the size needs a syscall, which adds up when drawing lots of
+    /// small things. With this turned off, the last known size is used until
+    /// [`TerminalDisplay::notify_resize`] is called, e.g. after receiving
+    /// crossterm's `Event::Resize`.
+    ///
+    /// [`TerminalDisplay::notify_resize`]: crate::TerminalDisplay::notify_resize
+    pub fn set_poll_size(&mut self, poll: bool) {
+        self.poll_size = poll;
+    }
+
+    /// Tell the display that the terminal might have been resized, so that it
+    /// checks its size again the next time something's drawn.
+    pub fn notify_resize(&mut self) {
+        self.resize_pending = true;
+    }
+
+    /// Set a function to be called with the new width and height of the
+    /// terminal in cells whenever it's resized, e.g. so that the app can lay
+    /// itself out again.
+    ///
+    /// The resize is noticed the next time something's drawn or flushed.
+    /// Whatever was drawn in any part of the display which gets cut off when
+    /// the terminal shrinks is kept, and comes back if it grows again, so
+    /// only the parts which have never been drawn to need drawing; clearing
+    /// the whole display forgets it.
+    pub fn on_resize(&mut self, f: impl FnMut(u16, u16) + Send + 'static) {
+        self.on_resize = Some(Box::new(f));
+    }
+
+    /// Set whether to assume that every character used to draw cells is a
+    /// single column wide, which is the default.
+    ///
+    /// Some terminals draw the block characters used by
+    /// [`RenderMode::HalfBlock`] and the bar modes (and the full block used by
+    /// the other modes) as two columns wide, since their width is ambiguous in
+    /// East Asian contexts; this shifts everything after them to the right,
+    /// skewing the image. Braille, sextant and ASCII characters aren't affected, and
+    /// neither are most quadrant characters.
+    ///
+    /// With this turned off, the cursor is moved to every cell explicitly
+    /// rather than relying on the terminal to move it along, so that every cell
+    /// at least ends up in the right place. This makes for a lot more output.
+    pub fn set_assume_narrow_blocks(&mut self, narrow: bool) {
+        self.stdout.assume_narrow_glyphs = narrow;
+    }
+
+    /// Set whether drawing anything outside the display returns
+    /// [`TerminalError::OutOfBounds`], rather than just being ignored like
+    /// embedded_graphics expects.
+    ///
+    /// This is off by default, but it's useful for catching coordinate
+    /// mistakes during development. Pixels which are inside the display still
+    /// get drawn when passed to [`DrawTarget::draw_iter`] alongside ones which
+    /// aren't, up until the first one which isn't; everything else checks the
+    /// whole area being drawn to before drawing anything.
+    ///
+    /// [`TerminalError::OutOfBounds`]: crate::TerminalError::OutOfBounds
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Set whether to flush after every draw, so that everything shows up on
+    /// the terminal straight away.
+    ///
+    /// This doesn't make the output unbuffered: each drawing method still
+    /// draws into the display's buffer like normal, and then calls
+    /// [`TerminalDisplay::flush`] before returning, which writes out whatever
+    /// changed and flushes the writer. The changes from a single draw are
+    /// still written in one go, with the cursor movement they need.
+    ///
+    /// This is off by default. Turning it on is useful for interactive tools
+    /// which change a few pixels at a time, and would otherwise have to
+    /// remember to flush after each change. The cost is throughput: every
+    /// call to [`TerminalDisplay::set_pixel`], [`DrawTarget::draw_iter`] and
+    /// the other drawing methods writes to the terminal and waits for it, and
+    /// embedded_graphics often draws a single shape or piece of text in many
+    /// separate calls. Anything bigger than a handful of pixels is better
+    /// drawn with this off and flushed once at the end.
+    ///
+    /// [`TerminalDisplay::set_pixel`]: crate::TerminalDisplay::set_pixel
+    pub fn set_auto_flush(&mut self, auto_flush: bool) {
+        self.auto_flush = auto_flush;
+    }
+
+    /// Set whether to use synchronized output, which stops the terminal from
+    /// showing a flush until it's been completely written so that half-drawn
+    /// frames never show up.
+    ///
+    /// This is off by default. It works by wrapping what each flush writes in
+    /// the escape sequences for turning private mode 2026 on and off, which
+    /// most modern terminals support. Terminals which don't should just ignore
+    /// them, the same as any other private mode they don't know about, so it's
+    /// usually safe to turn this on regardless; they'll just draw frames as
+    /// they arrive like they would otherwise.
+    pub fn set_synchronized_output(&mut self, synchronized: bool) {
+        self.stdout.synchronized_output = synchronized;
+    }
+
+    /// Set how to dither colors which the terminal can't display, when using a
+    /// color mode other than [`ColorMode::TrueColor`].
+    ///
+    /// This only affects things drawn after it's called.
+    pub fn set_dithering(&mut self, dithering: Dithering) {
+        self.dithering = dithering;
+    }
+
+    /// Set how to pick the colors of pixels when scaling images with
+    /// [`TerminalDisplay::blit_scaled`], which is [`Sampling::Nearest`] by
+    /// default.
+    ///
+    /// [`TerminalDisplay::blit_scaled`]: crate::TerminalDisplay::blit_scaled
+    pub fn set_sampling(&mut self, sampling: Sampling) {
+        self.sampling = sampling;
+    }
+
+    /// Set how everything drawn afterwards combines with what's already been
+    /// drawn underneath it, which is [`BlendMode::Normal`] by default.
+    ///
+    /// This applies to all drawing apart from [`DrawTarget::clear`], which
+    /// always replaces everything. It's usually only wanted for a few things,
+    /// like particles or lighting, so it's best to set it back afterwards.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    /// Show or hide the terminal's cursor.
+    ///
+    /// The display keeps track of whether the cursor's visible, so this only
+    /// writes anything if it's changing; that means the cursor should only be
+    /// shown and hidden through the display, rather than with crossterm
+    /// directly. The cursor is left the way this last set it when the display
+    /// is restored or dropped, whereas if it's only been hidden by
+    /// [`TerminalDisplayBuilder::hide_cursor`] or
+    /// [`TerminalDisplay::set_cursor_park`], it gets shown again. While the
+    /// display is suspended, this changes whether the cursor is hidden again
+    /// once it's resumed.
+    ///
+    /// [`TerminalDisplayBuilder::hide_cursor`]: crate::TerminalDisplayBuilder::hide_cursor
+    /// [`TerminalDisplay::set_cursor_park`]: crate::TerminalDisplay::set_cursor_park
+    pub fn set_cursor_visible(&mut self, visible: bool) -> Result<(), TerminalError> {
+        Ok(self.stdout.request_cursor_hidden(!visible)?)
+    }
+
+    /// Hide the terminal's cursor.
+    ///
+    /// This is the same as [`TerminalDisplay::set_cursor_visible`] with
+    /// `false`.
+    ///
+    /// [`TerminalDisplay::set_cursor_visible`]: crate::TerminalDisplay::set_cursor_visible
+    pub fn hide_cursor(&mut self) -> Result<(), TerminalError> {
+        self.set_cursor_visible(false)
+    }
+
+    /// Show the terminal's cursor.
+    ///
+    /// This is the same as [`TerminalDisplay::set_cursor_visible`] with
+    /// `true`.
+    ///
+    /// [`TerminalDisplay::set_cursor_visible`]: crate::TerminalDisplay::set_cursor_visible
+    pub fn show_cursor(&mut self) -> Result<(), TerminalError> {
+        self.set_cursor_visible(true)
+    }
+
+    /// Set where [`TerminalDisplay::present`] leaves the cursor, as a (column,
+    /// row) of the terminal, or `None` to hide it instead.
+    ///
+    /// By default, it's moved to the bottom-left corner of the terminal. If
+    /// the cursor gets hidden, it's shown again when the display is dropped,
+    /// or by calling [`TerminalDisplay::show_cursor`]; but it's hidden again
+    /// the next time a frame is presented, until this is changed back.
+    ///
+    /// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+    /// [`TerminalDisplay::show_cursor`]: crate::TerminalDisplay::show_cursor
+    pub fn set_cursor_park(&mut self, position: Option<(u16, u16)>) {
+        self.cursor_park = match position {
+            Some((column, row)) => CursorPark::At(column, row),
+            None => CursorPark::Hidden,
+        };
+    }
+
+    /// Get where [`TerminalDisplay::present`] leaves the cursor, or `None` if
+    /// it hides it instead.
+    ///
+    /// See [`TerminalDisplay::set_cursor_park`].
+    ///
+    /// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+    /// [`TerminalDisplay::set_cursor_park`]: crate::TerminalDisplay::set_cursor_park
+    pub fn cursor_park(&self) -> Option<(u16, u16)> {
+        match self.cursor_park {
+            CursorPark::BottomLeft => Some((0, self.terminal_size.1.saturating_sub(1))),
+            CursorPark::At(column, row) => Some((column, row)),
+            CursorPark::Hidden => None,
+        }
+    }
+
+    /// Put the terminal back the way it was before we started drawing to it,
+    /// by showing the cursor, resetting the colors, and leaving the alternate
+    /// screen and raw mode if need be.
+    ///
+    /// If the cursor's been shown or hidden with
+    /// [`TerminalDisplay::set_cursor_visible`], it's left that way instead.
+    /// This happens automatically when the display is dropped, but any errors
+    /// are ignored there.
+    ///
+    /// [`TerminalDisplay::set_cursor_visible`]: crate::TerminalDisplay::set_cursor_visible
+    pub fn restore(&mut self) -> Result<(), TerminalError> {
+        Ok(self.stdout.restore()?)
+    }
+
+    /// Give the terminal back temporarily, for running another program in it
+    /// like an editor or a pager.
+    ///
+    /// This puts the terminal back the way it was before we started drawing
+    /// to it, like [`TerminalDisplay::restore`], until
+    /// [`TerminalDisplay::resume`] is called. In the meantime, drawing still
+    /// works, but presenting and flushing don't write anything.
+    ///
+    /// [`TerminalDisplay::restore`]: crate::TerminalDisplay::restore
+    /// [`TerminalDisplay::resume`]: crate::TerminalDisplay::resume
+    pub fn suspend(&mut self) -> Result<(), TerminalError> {
+        Ok(self.stdout.suspend()?)
+    }
+
+    /// Take the terminal back after [`TerminalDisplay::suspend`], switching
+    /// back to the alternate screen, raw mode and a hidden cursor if that's
+    /// what it was using before, and then redraw everything.
+    ///
+    /// The whole display gets redrawn, since whatever ran in the meantime will
+    /// have drawn over it; this is done even if the display wasn't suspended.
+    ///
+    /// [`TerminalDisplay::suspend`]: crate::TerminalDisplay::suspend
+    pub fn resume(&mut self) -> Result<(), TerminalError> {
+        self.stdout.resume()?;
+        self.invalidate();
+        self.present()
+    }
+
+    /// Restore the terminal (see [`TerminalDisplay::restore`]), and then give
+    /// back the writer the display was writing to.
+    ///
+    /// [`TerminalDisplay::restore`]: crate::TerminalDisplay::restore
+    pub fn into_writer(mut self) -> Result<W, TerminalError> {
+        self.restore()?;
+        Ok(self.stdout.take_writer())
+    }
+
+    /// Get the color of the pixel at `point`, or `None` if it's outside the
+    /// bounds of the terminal.
+    pub fn get_pixel(&self, point: Point) -> Option<Color> {
+        let point = self.to_display(point)?;
+        let (width, height) = self.terminal_size().ok()?;
+        if !self.pixel_bounds(width, height).contains(point) {
+            return None;
+        }
+        // The terminal might have been resized since we last resized the buffer, in
+        // which case this pixel might not be in the buffer yet; but then nothing can
+        // have been drawn there either.
+        let color = self
+            .buffer
+            .get(point.y as usize)
+            .and_then(|row| row.get(point.x as usize));
+        Some(color.copied().unwrap_or(Color::BgColor))
+    }
+
+    /// Get every row of pixels drawn to the display, from top to bottom,
+    /// without copying them.
+    ///
+    /// This is for reading lots of pixels at once, which would be slow to do
+    /// with [`TerminalDisplay::get_pixel`]. Unlike that, the rows are relative
+    /// to the top-left corner of the display rather than to the offset set by
+    /// [`TerminalDisplay::set_offset`]. The pixels are as they were drawn,
+    /// with translucent colors already blended over whatever was underneath;
+    /// see [`Color::to_rgb888`] for turning them into RGB values.
+    ///
+    /// If the terminal's been resized since anything was last drawn, the rows
+    /// are still the old size until the next time something is.
+    ///
+    /// [`TerminalDisplay::get_pixel`]: crate::TerminalDisplay::get_pixel
+    /// [`TerminalDisplay::set_offset`]: crate::TerminalDisplay::set_offset
+    /// [`Color::to_rgb888`]: crate::Color::to_rgb888
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &[Color]> + '_ {
+        self.buffer.iter().map(Vec::as_slice)
+    }
+
+    /// Get the smallest rectangle of cells containing every cell which has
+    /// been drawn to since the last time this was called, or `None` if nothing
+    /// has been.
+    ///
+    /// The rectangle is measured in cells from the top-left corner of the
+    /// display, and covers text written with [`TerminalDisplay::put_str`] and
+    /// attributes changed with [`TerminalDisplay::set_attr`] as well as
+    /// pixels. It's useful for knowing which part of the display needs to be
+    /// composited when it's part of something bigger. Cells count as drawn to
+    /// even if what was drawn is the same as what was already there; the whole
+    /// display counts once it's been resized.
+    ///
+    /// This is separate from which cells need to be redrawn on the terminal,
+    /// so it isn't affected by flushing.
+    ///
+    /// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+    /// [`TerminalDisplay::set_attr`]: crate::TerminalDisplay::set_attr
+    pub fn take_damage(&mut self) -> Option<Rectangle> {
+        self.damage.take()
+    }
+
+    /// Set the point in drawing coordinates which appears at the top-left
+    /// corner of the display, for scrolling around content bigger than the
+    /// display.
+    ///
+    /// Everything drawn afterwards gets shifted up and left by `offset`, and so
+    /// do the points passed to [`TerminalDisplay::get_pixel`]. This
+    /// doesn't move anything which has already been drawn, so the contents
+    /// need to be drawn again after scrolling.
+    ///
+    /// [`TerminalDisplay::get_pixel`]: crate::TerminalDisplay::get_pixel
+    pub fn set_offset(&mut self, offset: Point) {
+        self.offset = offset;
+    }
+
+    /// Get the point in drawing coordinates which appears at the top-left
+    /// corner of the display.
+    pub fn offset(&self) -> Point {
+        self.offset
+    }
+
+    /// Get the size of the display in cells as (columns, rows).
+    ///
+    /// Unless the display has a fixed size, this is the size of the terminal.
+    pub fn char_size(&self) -> (u16, u16) {
+        cells(&self.config, self.size())
+    }
+
+    /// Get the column and row of the terminal containing the pixel at
+    /// `point`, or `None` if it's outside the bounds of the display.
+    ///
+    /// This is useful for positioning text alongside what's been drawn.
+    pub fn cell_for_point(&self, point: Point) -> Option<(u16, u16)> {
+        let (column, row) = self.display_cell(point)?;
+        let (origin_x, origin_y) = self.origin;
+        Some((
+            origin_x.saturating_add(column),
+            origin_y.saturating_add(row),
+        ))
+    }
+
+    /// Get the pixel at `column` and `row` of the terminal, or `None` if that
+    /// cell isn't part of the display; this is the opposite of
+    /// [`TerminalDisplay::cell_for_point`].
+    ///
+    /// This is meant for working out where mouse events from
+    /// [`TerminalDisplay::poll_event`] happened. Terminals only report which
+    /// cell the mouse is over, so `top_half` picks between the top and bottom
+    /// halves of the cell, which are the two pixels of a cell in
+    /// [`RenderMode::HalfBlock`]; either way, the pixel returned is the
+    /// left-most one in the first row of that half.
+    ///
+    /// [`TerminalDisplay::cell_for_point`]: crate::TerminalDisplay::cell_for_point
+    /// [`TerminalDisplay::poll_event`]: crate::TerminalDisplay::poll_event
+    /// [`RenderMode::HalfBlock`]: crate::RenderMode::HalfBlock
+    #[cfg(feature = "input")]
+    pub fn mouse_to_point(&self, column: u16, row: u16, top_half: bool) -> Option<Point> {
+        let (origin_x, origin_y) = self.origin;
+        let column = column.checked_sub(origin_x)?;
+        let row = row.checked_sub(origin_y)?;
+
+        let cell_size = self.config.cell_size();
+        let half = if top_half { 0 } else { cell_size.height / 2 };
+        // These can't overflow, since a `u16` times a cell size fits easily in an
+        // `i32`.
+        let point = Point::new(
+            (u32::from(column) * cell_size.width) as i32,
+            (u32::from(row) * cell_size.height + half) as i32,
+        );
+        let bounding_box = self.bounding_box();
+        if !bounding_box.contains(point) {
+            return None;
+        }
+        let point = self.config.orientation.apply(bounding_box.size, point);
+        Some(Point::new(
+            point.x.checked_add(self.offset.x)?,
+            point.y.checked_add(self.offset.y)?,
+        ))
+    }
+
+    /// Wait up to `timeout` for an input event from the terminal, returning
+    /// `None` if there wasn't one.
+    ///
+    /// This is just crossterm's event polling, so that things like keyboard
+    /// input can be handled in the same loop as drawing. Mouse events are only
+    /// reported once mouse capture has been enabled with crossterm's
+    /// `EnableMouseCapture`, and their positions can be turned into pixels with
+    /// [`TerminalDisplay::mouse_to_point`].
+    ///
+    /// [`TerminalDisplay::mouse_to_point`]: crate::TerminalDisplay::mouse_to_point
+    #[cfg(feature = "input")]
+    pub fn poll_event(&self, timeout: Duration) -> Result<Option<Event>, TerminalError> {
+        if crossterm::event::poll(timeout)? {
+            Ok(Some(crossterm::event::read()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Write `s` over the display as actual text, starting at `column` and
+    /// `row` (relative to the top-left of the display) and continuing to the
+    /// right, with `fg` and `bg` as the text's foreground and background
+    /// colors.
+    ///
+    /// The cells the text covers show it instead of whatever's drawn there,
+    /// until it's removed with [`TerminalDisplay::clear_text`]; drawing over
+    /// them still updates the buffer underneath, which is shown again once the
+    /// text is gone. Like everything else, it's only shown on the terminal on
+    /// the next flush.
+    ///
+    /// Every character is assumed to be one column wide, and any which would
+    /// end up past the right edge of the display are dropped.
+    ///
+    /// [`TerminalDisplay::clear_text`]: crate::TerminalDisplay::clear_text
+    pub fn put_str(
+        &mut self,
+        column: u16,
+        row: u16,
+        s: &str,
+        fg: Color,
+        bg: Color,
+    ) -> Result<(), TerminalError> {
+        self.resize()?;
+        let (columns, rows) = buffer_cells(&self.config, &self.buffer);
+        if row >= rows {
+            return Ok(());
+        }
+        for (column, character) in (column..columns).zip(s.chars()) {
+            self.damage_cell(column, row);
+            self.text.insert(
+                (column, row),
+                TextCell {
+                    character,
+                    fg,
+                    bg,
+                    drawn: false,
+                },
+            );
+        }
+        self.auto_flush()
+    }
+
+    /// Remove all the text written with [`TerminalDisplay::put_str`], so that
+    /// the cells it covered show what's been drawn there again.
+    ///
+    /// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+    pub fn clear_text(&mut self) {
+        for (column, row) in std::mem::take(&mut self.text).into_keys() {
+            self.damage_cell(column, row);
+            self.invalidate_cell(column, row);
+        }
+    }
+
+    /// Draw `text` in `font` with its baseline starting at `position`, which is
+    /// in drawing coordinates, returning where the next character would go.
+    ///
+    /// Unlike [`TerminalDisplay::put_str`], this draws the text as pixels, so
+    /// it can go anywhere rather than being lined up with the cells, and gets
+    /// drawn along with images and everything else. The font's pixels which
+    /// are on are drawn in `fg`, and the ones which are off are drawn in `bg`,
+    /// which can be [`Color::Transparent`] to leave what's behind the text
+    /// showing through. Anything which ends up outside the display is cut
+    /// off.
+    ///
+    /// This is the same as drawing an [`embedded_graphics::text::Text`] with a
+    /// [`MonoTextStyle`](embedded_graphics::mono_font::MonoTextStyle).
+    ///
+    /// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+    #[cfg(feature = "mono-font")]
+    pub fn draw_mono_text(
+        &mut self,
+        text: &str,
+        position: Point,
+        font: &embedded_graphics::mono_font::MonoFont<'_>,
+        fg: Color,
+        bg: Color,
+    ) -> Result<Point, TerminalError> {
+        use embedded_graphics::mono_font::MonoTextStyleBuilder;
+        use embedded_graphics::text::{Baseline, Text};
+        use embedded_graphics::Drawable;
+
+        let style = MonoTextStyleBuilder::new()
+            .font(font)
+            .text_color(fg)
+            .background_color(bg)
+            .build();
+        Text::with_baseline(text, position, style, Baseline::Alphabetic).draw(self)
+    }
+
+    /// Draw the cell containing the pixel at `point` with the given text
+    /// attributes, replacing any it already had.
+    ///
+    /// Attributes apply to whole cells, whether they're drawn from pixels or
+    /// from text written with [`TerminalDisplay::put_str`]. They're turned off
+    /// again after each cell, so they don't spread to the cells around it.
+    /// Nothing happens if `point` is outside the display; pass
+    /// [`Attributes::NONE`] to go back to drawing the cell normally.
+    ///
+    /// Not every terminal supports every attribute, and some (like blinking)
+    /// are often turned off by users.
+    ///
+    /// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+    pub fn set_attr(&mut self, point: Point, attr: Attributes) {
+        let Some((column, row)) = self.display_cell(point) else {
+            return;
+        };
+        if cell_attributes(&self.attributes, column, row) == attr {
+            return;
+        }
+        if attr.is_empty() {
+            self.attributes.remove(&(column, row));
+        } else {
+            self.attributes.insert((column, row), attr);
+        }
+        self.damage_cell(column, row);
+        self.invalidate_cell(column, row);
+    }
+
+    /// Remove the attributes from every cell which was given some with
+    /// [`TerminalDisplay::set_attr`].
+    ///
+    /// [`TerminalDisplay::set_attr`]: crate::TerminalDisplay::set_attr
+    pub fn clear_attrs(&mut self) {
+        for (column, row) in std::mem::take(&mut self.attributes).into_keys() {
+            self.damage_cell(column, row);
+            self.invalidate_cell(column, row);
+        }
+    }
+
+    /// Take a copy of everything that's currently drawn to the display,
+    /// including text and attributes, so that it can be put back later with
+    /// [`TerminalDisplay::restore_snapshot`].
+    ///
+    /// This is useful for things like tooltips, which get drawn over the top of
+    /// everything else and then taken away again.
+    ///
+    /// [`TerminalDisplay::restore_snapshot`]: crate::TerminalDisplay::restore_snapshot
+    pub fn snapshot(&self) -> BufferSnapshot {
+        BufferSnapshot {
+            buffer: self.buffer.clone(),
+            text: self.text.clone(),
+            attributes: self.attributes.clone(),
+        }
+    }
+
+    /// Put back everything that was drawn to the display when `snapshot` was
+    /// taken with [`TerminalDisplay::snapshot`].
+    ///
+    /// Like any other drawing, this isn't shown until the display is flushed,
+    /// and only the cells which have changed get redrawn then. If the display
+    /// has changed size since the snapshot was taken, this returns
+    /// [`TerminalError::SizeMismatch`] and leaves the display alone.
+    ///
+    /// [`TerminalDisplay::snapshot`]: crate::TerminalDisplay::snapshot
+    pub fn restore_snapshot(&mut self, snapshot: &BufferSnapshot) -> Result<(), TerminalError> {
+        self.resize()?;
+        let (width, height) = buffer_size(&self.buffer);
+        let size = Size::new(width as u32, height as u32);
+        if snapshot.size() != size {
+            return Err(TerminalError::SizeMismatch {
+                expected: snapshot.size(),
+                actual: size,
+            });
+        }
+
+        for (y, row) in snapshot.buffer.iter().enumerate() {
+            self.buffer[y].copy_from_slice(row);
+        }
+        self.damage_pixels(&Rectangle::new(Point::zero(), size));
+
+        // Text which is the same in both can be left as it is, but anything else has
+        // to be redrawn, and the pixels under text which is going away have to be
+        // redrawn over it.
+        let old_text = std::mem::take(&mut self.text);
+        for (&(column, row), &cell) in &snapshot.text {
+            let drawn = old_text.get(&(column, row)).is_some_and(|old| {
+                old.drawn && (old.character, old.fg, old.bg) == (cell.character, cell.fg, cell.bg)
+            });
+            self.text.insert((column, row), TextCell { drawn, ..cell });
+        }
+        for (column, row) in old_text.into_keys() {
+            if !self.text.contains_key(&(column, row)) {
+                self.invalidate_cell(column, row);
+            }
+        }
+
+        let old_attributes = std::mem::replace(&mut self.attributes, snapshot.attributes.clone());
+        for &(column, row) in old_attributes.keys().chain(snapshot.attributes.keys()) {
+            if old_attributes.get(&(column, row)) != snapshot.attributes.get(&(column, row)) {
+                self.invalidate_cell(column, row);
+            }
+        }
+        Ok(())
+    }
+
+    /// Restrict all drawing to `area` until [`TerminalDisplay::pop_clip`] is
+    /// called, on top of any other areas drawing is already restricted to.
+    ///
+    /// Anything drawn outside the area is ignored, the same as anything drawn
+    /// outside the display. That includes [`DrawTarget::clear`], which only
+    /// clears the area. `area` is in drawing coordinates, so it moves along
+    /// with the offset set with [`TerminalDisplay::set_offset`].
+    ///
+    /// [`TerminalDisplay::pop_clip`]: crate::TerminalDisplay::pop_clip
+    /// [`TerminalDisplay::set_offset`]: crate::TerminalDisplay::set_offset
+    pub fn push_clip(&mut self, area: Rectangle) {
+        self.clip.push(area);
+    }
+
+    /// Undo the last call to [`TerminalDisplay::push_clip`], if there's one
+    /// which hasn't been undone yet.
+    ///
+    /// [`TerminalDisplay::push_clip`]: crate::TerminalDisplay::push_clip
+    pub fn pop_clip(&mut self) {
+        self.clip.pop();
+    }
+
+    /// Set the color of the pixel at `point`, if it's within the bounds of the
+    /// display.
+    ///
+    /// This is the same as drawing a single pixel with
+    /// [`DrawTarget::draw_iter`], but more convenient. Like everything else,
+    /// it's only shown on the terminal on the next flush.
+    pub fn set_pixel(&mut self, point: Point, color: Color) -> Result<(), TerminalError> {
+        let (width, height) = self.resize()?;
+        let bounding_box = self.pixel_bounds(width, height);
+        let clip_area = self.clip_area(&bounding_box);
+        self.put_pixel(&bounding_box, &clip_area, point, color)?;
+        self.auto_flush()
+    }
+
+    /// Fill `area` of the display with `color`, like [`DrawTarget::clear`]
+    /// but only for part of the display.
+    ///
+    /// This is the same as [`DrawTarget::fill_solid`]; parts of `area` outside
+    /// the display are ignored, unless the display is
+    /// [strict](TerminalDisplay::set_strict).
+    pub fn clear_region(&mut self, area: &Rectangle, color: Color) -> Result<(), TerminalError> {
+        let (width, height) = self.resize()?;
+        let bounding_box = self.pixel_bounds(width, height);
+
+        self.check_area(&bounding_box, area)?;
+        let clamped_area = self.clamp_area(&self.clip_area(&bounding_box), area);
+        self.fill_area(&clamped_area, color, self.blend_mode);
+        self.auto_flush()
+    }
+
+    /// Draw an antialiased line from `from` to `to`, using Xiaolin Wu's
+    /// algorithm.
+    ///
+    /// Rather than picking a single pixel for each column (or row, for steep
+    /// lines) like [`embedded_graphics::primitives::Line`], this splits each
+    /// one between the two pixels the line passes between, according to how
+    /// close it is to each of them. Those get `color` drawn over them as a
+    /// [`Color::Rgba`] with its alpha scaled down to match, blended with
+    /// whatever's already there using the [`BlendMode`]; pixels the line goes
+    /// straight through get `color` itself. This looks a lot smoother than an
+    /// aliased line, especially with pixels as coarse as
+    /// [`RenderMode::HalfBlock`]'s.
+    ///
+    /// The parts of the line which are off the display are skipped without
+    /// stepping through them. In strict mode, it's an error for either end of
+    /// the line to be off the display.
+    ///
+    /// [`embedded_graphics::primitives::Line`]: https://docs.rs/embedded-graphics/0.7/embedded_graphics/primitives/line/struct.Line.html
+    /// [`BlendMode`]: crate::BlendMode
+    /// [`RenderMode::HalfBlock`]: crate::RenderMode::HalfBlock
+    pub fn draw_line_aa(
+        &mut self,
+        from: Point,
+        to: Point,
+        color: Color,
+    ) -> Result<(), TerminalError> {
+        let (width, height) = self.resize()?;
+        let bounding_box = self.pixel_bounds(width, height);
+        let clip_area = self.clip_area(&bounding_box);
+        let on_display = |point| {
+            self.to_display(point)
+                .is_some_and(|point| bounding_box.contains(point))
+        };
+        if self.strict {
+            if let Some(&point) = [from, to].iter().find(|&&point| !on_display(point)) {
+                return Err(TerminalError::OutOfBounds { point });
+            }
+        }
+
+        // Step along whichever axis the line is longer in, calling it `a` and the other
+        // one `b`. This is all done in `i64`s, so that it can't overflow.
+        let (dx, dy) = (
+            i64::from(to.x) - i64::from(from.x),
+            i64::from(to.y) - i64::from(from.y),
+        );
+        let steep = dy.abs() > dx.abs();
+        let split = |point: Point| match steep {
+            false => (i64::from(point.x), i64::from(point.y)),
+            true => (i64::from(point.y), i64::from(point.x)),
+        };
+        let (mut start, mut end) = (split(from), split(to));
+        if start.0 > end.0 {
+            std::mem::swap(&mut start, &mut end);
+        }
+        let gradient = match end.0 - start.0 {
+            0 => 0.0,
+            length => (end.1 - start.1) as f64 / length as f64,
+        };
+
+        // Only step through the part of the line which is within the clip area.
+        let (clip_start, clip_len, offset) = match steep {
+            false => (clip_area.top_left.x, clip_area.size.width, self.offset.x),
+            true => (clip_area.top_left.y, clip_area.size.height, self.offset.y),
+        };
+        let clip_start = i64::from(clip_start) + i64::from(offset);
+        let first = start.0.max(clip_start);
+        let last = end.0.min(clip_start + i64::from(clip_len) - 1);
+
+        let mut pixels = Vec::new();
+        for a in first..=last {
+            let b = start.1 as f64 + gradient * (a - start.0) as f64;
+            let (b_floor, fraction) = (b.floor(), b - b.floor());
+            for (b, coverage) in [(b_floor, 1.0 - fraction), (b_floor + 1.0, fraction)] {
+                // `b` is between the ends of the line, so it fits in an `i32`.
+                let (a, b) = (a as i32, b as i32);
+                let point = if steep {
+                    Point::new(b, a)
+                } else {
+                    Point::new(a, b)
+                };
+                // The pixels just off the sides of the line can fall off the display even if
+                // its ends don't, which isn't an error.
+                if coverage > 0.0 && on_display(point) {
+                    pixels.push(Pixel(point, color.with_coverage(coverage as f32)));
+                }
+            }
+        }
+        for Pixel(point, color) in pixels {
+            self.put_pixel(&bounding_box, &clip_area, point, color)?;
+        }
+        self.auto_flush()
+    }
+
+    /// Fill `area` of the display with a checkerboard of `square_size` by
+    /// `square_size` squares, alternating between `a` and `b` starting with
+    /// `a` in the top-left corner.
+    ///
+    /// This is the usual way of showing which parts of an image are
+    /// transparent: draw the checkerboard, and then draw the image over the top
+    /// of it. A `square_size` of 0 is treated as 1.
+    pub fn fill_checker(
+        &mut self,
+        area: &Rectangle,
+        a: Color,
+        b: Color,
+        square_size: u32,
+    ) -> Result<(), TerminalError> {
+        let (width, height) = self.resize()?;
+        let bounding_box = self.pixel_bounds(width, height);
+
+        self.check_area(&bounding_box, area)?;
+        let clamped_area = self.clamp_area(&self.clip_area(&bounding_box), area);
+        let Some(clamped_bottom_right) = clamped_area.bottom_right() else {
+            return Ok(());
+        };
+
+        // Work out which squares overlap the display, and where each one starts and
+        // ends once it's clamped to the display. This is done in `i64`s, since the
+        // squares can start far enough off the display to overflow an `i32`.
+        let square_size = i64::from(square_size.max(1));
+        let squares = |start: i32, offset: i32, clamped_start: i32, clamped_end: i32| {
+            let start = i64::from(start) - i64::from(offset);
+            let (clamped_start, clamped_end) = (i64::from(clamped_start), i64::from(clamped_end));
+            let first = (clamped_start - start) / square_size;
+            let last = (clamped_end - start) / square_size;
+            (first..=last).map(move |i| {
+                let square_start = (start + i * square_size).max(clamped_start);
+                let square_end = (start + (i + 1) * square_size).min(clamped_end + 1);
+                // These are both clamped to the display, so they fit in an `i32`.
+                (i, square_start as i32, (square_end - square_start) as u32)
+            })
+        };
+
+        for (row, y, square_height) in squares(
+            area.top_left.y,
+            self.offset.y,
+            clamped_area.top_left.y,
+            clamped_bottom_right.y,
+        ) {
+            for (column, x, square_width) in squares(
+                area.top_left.x,
+                self.offset.x,
+                clamped_area.top_left.x,
+                clamped_bottom_right.x,
+            ) {
+                let color = if (row + column) % 2 == 0 { a } else { b };
+                let square =
+                    Rectangle::new(Point::new(x, y), Size::new(square_width, square_height));
+                self.fill_area(&square, color, self.blend_mode);
+            }
+        }
+        self.auto_flush()
+    }
+
+    /// Copy an image onto the display with its top-left corner at `top_left`,
+    /// where `data` is the image's pixels in row-major order and `width` is
+    /// the width of the image.
+    ///
+    /// This is equivalent to [`DrawTarget::fill_contiguous`], but copies
+    /// straight out of `data`, which is faster for apps which keep their own
+    /// framebuffer. Any pixels left over at the end of `data` which don't make
+    /// up a whole row are ignored.
+    pub fn blit(
+        &mut self,
+        top_left: Point,
+        data: &[Color],
+        width: u32,
+    ) -> Result<(), TerminalError> {
+        let (terminal_width, terminal_height) = self.resize()?;
+        let bounding_box = self.pixel_bounds(terminal_width, terminal_height);
+        if width == 0 {
+            return Ok(());
+        }
+
+        let height = (data.len() / width as usize).min(u32::MAX as usize) as u32;
+        let area = Rectangle::new(top_left, Size::new(width, height));
+        self.check_area(&bounding_box, &area)?;
+        let clamped_area = self.clamp_area(&self.clip_area(&bounding_box), &area);
+        if clamped_area.is_zero_sized() {
+            return Ok(());
+        }
+
+        let (left_padding, _, top_padding) = self.padding(&area, &clamped_area);
+        self.copy_in(
+            &clamped_area,
+            data,
+            width as usize,
+            left_padding,
+            top_padding,
+        );
+        self.auto_flush()
+    }
+
+    /// Draw a run-length encoded image onto the display with its top-left
+    /// corner at `top_left`, where `runs` is the image's pixels in row-major
+    /// order as (color, count) pairs and `width` is the width of the image.
+    ///
+    /// This is like [`TerminalDisplay::blit`], but the runs are expanded as
+    /// they're drawn rather than having to be turned into an array of pixels
+    /// first. Runs can carry on from the end of one row onto the next, and runs
+    /// of [`Color::Transparent`] are skipped over, which is useful for sprites
+    /// with lots of empty space around them. Any pixels left over at the end
+    /// which don't make up a whole row are ignored.
+    ///
+    /// [`TerminalDisplay::blit`]: crate::TerminalDisplay::blit
+    pub fn blit_rle(
+        &mut self,
+        top_left: Point,
+        runs: &[(Color, u16)],
+        width: u32,
+    ) -> Result<(), TerminalError> {
+        if width == 0 {
+            return Ok(());
+        }
+        let len: u64 = runs.iter().map(|&(_, count)| u64::from(count)).sum();
+        let height = (len / u64::from(width)).min(u32::MAX.into()) as u32;
+        let colors = runs
+            .iter()
+            .flat_map(|&(color, count)| std::iter::repeat_n(color, count.into()));
+        self.fill_contiguous(&Rectangle::new(top_left, Size::new(width, height)), colors)
+    }
+
+    /// Copy the part of an image which covers `clamped_area` of the display into
+    /// the buffer, where `data` is the image's pixels in row-major order,
+    /// `width` is the width of the image, and the top-left corner of
+    /// `clamped_area` is `left_padding` pixels to the right and `top_padding`
+    /// pixels down from the image's.
+    fn copy_in(
+        &mut self,
+        clamped_area: &Rectangle,
+        data: &[Color],
+        width: usize,
+        left_padding: usize,
+        top_padding: usize,
+    ) {
+        // The clamped area is inside both the display and the image, so all of these
+        // are positive and within bounds.
+        let start_x = clamped_area.top_left.x as usize;
+        let end_x = start_x + clamped_area.size.width as usize;
+
+        self.damage_pixels(clamped_area);
+        let mut dither = self.dither(end_x - start_x);
+        let blend_mode = self.blend_mode;
+
+        for (i, y) in clamped_area.rows().enumerate() {
+            let start = (top_padding + i) * width + left_padding;
+            let source = &data[start..start + (end_x - start_x)];
+            let row = &mut self.buffer[y as usize][start_x..end_x];
+            for (x, (pixel, &color)) in row.iter_mut().zip(source).enumerate() {
+                if color == Color::Transparent {
+                    continue;
+                }
+                let color = blend_mode.blend(color, *pixel);
+                *pixel = match &mut dither {
+                    Some(dither) => dither.quantize(x, color),
+                    None => color,
+                };
+            }
+
+            if let Some(dither) = &mut dither {
+                dither.next_row();
+            }
+        }
+        self.finish_dither(dither);
+    }
+
+    /// Scale an image to fit the display, where `source` is the image's pixels
+    /// in row-major order and `source_size` is its size.
+    ///
+    /// `fit` controls how the image's aspect ratio is dealt with. The image is
+    /// scaled using the sampling set with [`TerminalDisplay::set_sampling`],
+    /// and apart from that it gets drawn the same way as with
+    /// [`TerminalDisplay::blit`]. It's always fitted to the whole display,
+    /// regardless of the offset set with [`TerminalDisplay::set_offset`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `source` has fewer than `source_size.width *
+    /// source_size.height` pixels.
+    ///
+    /// [`TerminalDisplay::set_sampling`]: crate::TerminalDisplay::set_sampling
+    /// [`TerminalDisplay::blit`]: crate::TerminalDisplay::blit
+    /// [`TerminalDisplay::set_offset`]: crate::TerminalDisplay::set_offset
+    pub fn blit_scaled(
+        &mut self,
+        source: &[Color],
+        source_size: Size,
+        fit: Fit,
+    ) -> Result<(), TerminalError> {
+        assert!(
+            source.len() as u64 >= u64::from(source_size.width) * u64::from(source_size.height),
+            "image is smaller than its size"
+        );
+        let (width, height) = self.resize()?;
+        let bounding_box = self.pixel_bounds(width, height);
+        self.blit_fitted(
+            source,
+            source_size,
+            fit,
+            &bounding_box,
+            &Rectangle::new(self.offset, bounding_box.size),
+        )
+    }
+
+    /// Load the image at `path` and scale it to fit the part of the display
+    /// from `top_left` (in drawing coordinates) to the bottom-right corner.
+    ///
+    /// This works the same way as [`TerminalDisplay::blit_scaled`] apart from
+    /// where the image goes, so pass `Point::zero()` to fit it to the whole
+    /// display. Wherever the image is translucent, it's blended over the
+    /// display's canvas color (see
+    /// [`TerminalDisplayBuilder::canvas_color`]), or the RGB value set for
+    /// the terminal's background with [`TerminalDisplay::set_default_colors`]
+    /// if there isn't one.
+    ///
+    /// Any format the `image` crate can decode is supported, which by default
+    /// is only PNG and GIF; enabling more of its features in your own
+    /// `Cargo.toml` adds the others.
+    ///
+    /// [`TerminalDisplay::blit_scaled`]: crate::TerminalDisplay::blit_scaled
+    /// [`TerminalDisplayBuilder::canvas_color`]: crate::TerminalDisplayBuilder::canvas_color
+    /// [`TerminalDisplay::set_default_colors`]: crate::TerminalDisplay::set_default_colors
+    #[cfg(feature = "image")]
+    pub fn draw_image(
+        &mut self,
+        path: impl AsRef<Path>,
+        top_left: Point,
+        fit: Fit,
+    ) -> Result<(), TerminalError> {
+        let background = self
+            .config
+            .canvas_color
+            .to_rgb888()
+            .unwrap_or(self.default_colors.0);
+        let (source, source_size) = export::load_image(path, background)?;
+
+        let (width, height) = self.resize()?;
+        let bounding_box = self.pixel_bounds(width, height);
+        // Work out how far `top_left` is from the bottom-right corner, in `i64`s since
+        // it can be anywhere.
+        let remaining = |start: i32, offset: i32, len: u32| {
+            let start = i64::from(start) - i64::from(offset);
+            (i64::from(len) - start).clamp(0, u32::MAX.into()) as u32
+        };
+        let area = Rectangle::new(
+            top_left,
+            Size::new(
+                remaining(top_left.x, self.offset.x, bounding_box.size.width),
+                remaining(top_left.y, self.offset.y, bounding_box.size.height),
+            ),
+        );
+        self.blit_fitted(&source, source_size, fit, &bounding_box, &area)
+    }
+
+    /// Scale an image to fit `area`, which is in drawing coordinates, where
+    /// `source` is the image's pixels in row-major order and `source_size` is
+    /// its size.
+    fn blit_fitted(
+        &mut self,
+        source: &[Color],
+        source_size: Size,
+        fit: Fit,
+        bounding_box: &Rectangle,
+        area: &Rectangle,
+    ) -> Result<(), TerminalError> {
+        if source_size.width == 0 || source_size.height == 0 {
+            return Ok(());
+        }
+
+        let fitted = fit.area(source_size, area.size);
+        let (Some(x), Some(y)) = (
+            area.top_left.x.checked_add(fitted.top_left.x),
+            area.top_left.y.checked_add(fitted.top_left.y),
+        ) else {
+            // The image is so far off the display that there's nothing to draw.
+            return Ok(());
+        };
+        let fitted = Rectangle::new(Point::new(x, y), fitted.size);
+        let clamped_area = self.clamp_area(&self.clip_area(bounding_box), &fitted);
+        if clamped_area.is_zero_sized() {
+            return Ok(());
+        }
 
-/// Get the size of the terminal in pixels from its size in rows/columns.
-fn size(width: u16, height: u16) -> Size {
-    Size::new(u32::from(width), 2 * u32::from(height))
-}
+        // The clamped area is inside the fitted area, so these are all inside it as
+        // well, and fit in a `u32`.
+        let (left_padding, _, top_padding) = self.padding(&fitted, &clamped_area);
+        let mut scaled = std::mem::take(&mut self.scale_scratch);
+        scaled.clear();
+        for y in 0..clamped_area.size.height as usize {
+            for x in 0..clamped_area.size.width as usize {
+                let point = ((left_padding + x) as u32, (top_padding + y) as u32);
+                scaled.push(
+                    self.sampling
+                        .sample(source, source_size, fitted.size, point),
+                );
+            }
+        }
+        self.copy_in(
+            &clamped_area,
+            &scaled,
+            clamped_area.size.width as usize,
+            0,
+            0,
+        );
+        self.scale_scratch = scaled;
+        self.auto_flush()
+    }
 
-/// Get the bounding box of the terminal in pixels from its size in
-/// rows/columns.
-fn bounding_box(width: u16, height: u16) -> Rectangle {
-    Rectangle {
-        top_left: Point::zero(),
-        size: size(width, height),
+    /// Set the RGB values to use for the terminal's default background and
+    /// foreground colors when exporting images or drawing sixels, since the
+    /// real ones can't be known.
+    ///
+    /// These start out as black and white.
+    #[cfg(any(feature = "image", feature = "sixel"))]
+    pub fn set_default_colors(&mut self, background: Rgb888, foreground: Rgb888) {
+        self.default_colors = (background, foreground);
     }
-}
 
-fn write_cell(mut stdout: impl Write, top_color: Color, bottom_color: Color) -> io::Result<()> {
-    match (top_color, bottom_color) {
-        (Color::BgColor, Color::BgColor) => {
-            stdout.queue(style::SetBackgroundColor(CrosstermColor::Reset))?;
-            stdout.write_all(" ".as_bytes())
-        }
-        (Color::FgColor, Color::FgColor) => {
-            stdout.queue(style::SetForegroundColor(CrosstermColor::Reset))?;
-            stdout.write_all("█".as_bytes())
+    /// Save the contents of the display to a PNG image at `path`, with one
+    /// pixel per pixel of the display.
+    ///
+    /// Colors are exported as they'd be shown in the display's color mode,
+    /// using the xterm palette for named colors, and the colors set with
+    /// [`TerminalDisplay::set_default_colors`] for the terminal's default
+    /// colors.
+    ///
+    /// [`TerminalDisplay::set_default_colors`]: crate::TerminalDisplay::set_default_colors
+    #[cfg(feature = "image")]
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), TerminalError> {
+        export::to_image(&self.buffer, &self.config, self.default_colors)
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(export::image_error)
+    }
+
+    /// Start recording an animated GIF at `path`, which gets a frame added
+    /// every time [`TerminalDisplay::present`] is called.
+    ///
+    /// Each frame is shown for as long as it was actually shown on the
+    /// terminal, except that frames presented in very quick succession get
+    /// merged to keep the file size down. Colors are exported the same way as
+    /// with [`TerminalDisplay::save_png`].
+    ///
+    /// The GIF isn't complete until [`TerminalDisplay::finish_gif`] is called.
+    /// Starting a new recording finishes the previous one.
+    ///
+    /// [`TerminalDisplay::present`]: crate::TerminalDisplay::present
+    /// [`TerminalDisplay::save_png`]: crate::TerminalDisplay::save_png
+    /// [`TerminalDisplay::finish_gif`]: crate::TerminalDisplay::finish_gif
+    #[cfg(feature = "image")]
+    pub fn record_gif(&mut self, path: impl AsRef<Path>) -> Result<(), TerminalError> {
+        self.finish_gif()?;
+        self.gif = Some(export::GifRecorder::new(path)?);
+        Ok(())
+    }
+
+    /// Finish recording the GIF started with [`TerminalDisplay::record_gif`],
+    /// if there is one.
+    ///
+    /// [`TerminalDisplay::record_gif`]: crate::TerminalDisplay::record_gif
+    #[cfg(feature = "image")]
+    pub fn finish_gif(&mut self) -> Result<(), TerminalError> {
+        match self.gif.take() {
+            Some(gif) => gif.finish(),
+            None => Ok(()),
         }
-        (top_color, bottom_color)
-            if top_color != Color::FgColor && bottom_color != Color::BgColor =>
-        {
-            stdout.queue(style::SetBackgroundColor(top_color.to_crossterm_color()))?;
-            stdout.queue(style::SetForegroundColor(bottom_color.to_crossterm_color()))?;
-            stdout.write_all("▄".as_bytes())
+    }
+
+    /// Render the entire contents of the display to a string of the escape
+    /// sequences which would redraw it from scratch.
+    ///
+    /// This doesn't depend on what's already been flushed, so it's useful for
+    /// snapshotting what's been drawn.
+    pub fn to_ansi_string(&self) -> String {
+        let mut out = TerminalWriter::new(Vec::new());
+        out.assume_narrow_glyphs = self.stdout.assume_narrow_glyphs;
+        // Writing to a `Vec` can't fail.
+        self.redraw(&mut out).unwrap();
+        // We only ever write valid UTF-8.
+        String::from_utf8(out.take_writer()).unwrap()
+    }
+
+    /// Write every cell in the buffer to `out`.
+    fn redraw(&self, out: &mut TerminalWriter<impl Write>) -> io::Result<()> {
+        let (columns, rows) = buffer_cells(&self.config, &self.buffer);
+        let pixel_count = self.config.render_mode.cell_pixel_count();
+        let (origin_x, origin_y) = self.origin;
+        #[cfg(feature = "sixel")]
+        if !self.config.draws_cells() {
+            write_sixel_rows(
+                out,
+                &self.config,
+                &self.buffer,
+                self.default_colors,
+                self.origin,
+                columns,
+                0..rows,
+            )?;
+            for row in 0..rows {
+                for column in 0..columns {
+                    if let Some(text) = self.text.get(&(column, row)) {
+                        out.move_to(
+                            origin_x.saturating_add(column),
+                            origin_y.saturating_add(row),
+                        )?;
+                        out.write_text(&self.config, text.character, text.fg, text.bg)?;
+                    }
+                }
+            }
+            return Ok(());
         }
-        (top_color, bottom_color) => {
-            stdout.queue(style::SetBackgroundColor(bottom_color.to_crossterm_color()))?;
-            stdout.queue(style::SetForegroundColor(top_color.to_crossterm_color()))?;
-            stdout.write_all("▀".as_bytes())
+        for row in 0..rows {
+            out.move_to(origin_x, origin_y.saturating_add(row))?;
+            let mut column = 0;
+            while column < columns {
+                if let Some(text) = self.text.get(&(column, row)) {
+                    out.move_to(
+                        origin_x.saturating_add(column),
+                        origin_y.saturating_add(row),
+                    )?;
+                    out.write_text(&self.config, text.character, text.fg, text.bg)?;
+                    column += 1;
+                    continue;
+                }
+
+                let contents = cell_contents(&self.config, &self.buffer, column, row);
+                let mut end = column + 1;
+                while end < columns
+                    && !self.text.contains_key(&(end, row))
+                    && cell_contents(&self.config, &self.buffer, end, row) == contents
+                {
+                    end += 1;
+                }
+                // This doesn't write anything unless the cursor might not be where we think,
+                // like after a wide glyph.
+                out.move_to(
+                    origin_x.saturating_add(column),
+                    origin_y.saturating_add(row),
+                )?;
+                out.write_cells(&self.config, &contents[..pixel_count], end - column)?;
+                column = end;
+            }
         }
+        Ok(())
     }
-}
 
-/// An implementation of `embedded_graphics::DrawTarget` for the terminal using
-/// crossterm.
-///
-/// A pixel is half of a character in the terminal, since they're usually about
-/// 1x2.
-///
-/// To show the rendered image, the buffer must be flushed by calling
-/// [`TerminalDisplay::flush`].
-///
-/// [`TerminalDisplay::flush`]: crate::TerminalDisplay::flush
-pub struct TerminalDisplay {
-    /// A tuple of the (top_color, bottom_color) of every cell.
-    ///
-    /// This is needed because it's impossible to get back the color of a cell,
-    /// and we need to preserve the color of the other half of the cell when
-    /// writing a single pixel.
-    buffer: Vec<Vec<(Color, Color)>>,
-    /// We need to store this between runs so that
-    stdout: BufWriter<Stdout>,
-}
+    /// Record that the given cell has been written to the terminal as it
+    /// currently is in the buffer.
+    fn commit_cell(&mut self, column: u16, row: u16) {
+        let (xs, ys) = cell_pixels(&self.config, buffer_size(&self.buffer), column, row);
+        for y in ys {
+            for x in xs.clone() {
+                self.committed[y][x] = Some(self.buffer[y][x]);
+            }
+        }
+    }
 
-impl TerminalDisplay {
-    pub fn new() -> io::Result<Self> {
-        let mut this = Self {
-            buffer: Vec::new(),
-            stdout: BufWriter::new(io::stdout()),
+    /// Add the cells containing `area` of the buffer to the damage returned by
+    /// [`TerminalDisplay::take_damage`].
+    fn damage_pixels(&mut self, area: &Rectangle) {
+        let Some(bottom_right) = area.bottom_right() else {
+            return;
         };
-        this.resize()?;
-        Ok(this)
+        let (width, height) = buffer_size(&self.buffer);
+        let size = Size::new(width as u32, height as u32);
+        let cell_size = self.config.cell_size();
+        // Flipping the display can swap which corner is which, so the corners are
+        // sorted out by `with_corners`.
+        let [top_left, bottom_right] = [area.top_left, bottom_right].map(|point| {
+            let point = self.config.orientation.apply(size, point);
+            Point::new(
+                point.x / cell_size.width as i32,
+                point.y / cell_size.height as i32,
+            )
+        });
+        self.damage_cells(Rectangle::with_corners(top_left, bottom_right));
     }
 
-    pub fn flush(&mut self) -> io::Result<()> {
-        self.stdout.flush()
+    /// Add the given cell to the damage returned by
+    /// [`TerminalDisplay::take_damage`].
+    fn damage_cell(&mut self, column: u16, row: u16) {
+        self.damage_cells(Rectangle::new(
+            Point::new(column.into(), row.into()),
+            Size::new(1, 1),
+        ));
     }
 
-    /// Resize the buffer to the correct size if it's changed, and return the
-    /// current size of the terminal as (width, height).
-    fn resize(&mut self) -> io::Result<(u16, u16)> {
-        let (width, height) = terminal::size()?;
-        if self.buffer.get(0).map_or(0, |row| row.len()) != width.into() {
-            for row in &mut self.buffer {
-                row.resize(width.into(), (Color::BgColor, Color::BgColor));
+    /// Add `cells` to the damage returned by [`TerminalDisplay::take_damage`].
+    fn damage_cells(&mut self, cells: Rectangle) {
+        self.damage = Some(match self.damage {
+            // Neither of these are zero-sized, so they both have bottom-right corners.
+            Some(damage) => Rectangle::with_corners(
+                damage.top_left.component_min(cells.top_left),
+                damage
+                    .bottom_right()
+                    .unwrap()
+                    .component_max(cells.bottom_right().unwrap()),
+            ),
+            None => cells,
+        });
+    }
+
+    /// Mark the given cell as needing to be redrawn on the next flush, whether
+    /// it's drawn from pixels or text.
+    fn invalidate_cell(&mut self, column: u16, row: u16) {
+        if let Some(text) = self.text.get_mut(&(column, row)) {
+            text.drawn = false;
+        }
+        let (xs, ys) = cell_pixels(&self.config, buffer_size(&self.buffer), column, row);
+        for y in ys {
+            self.committed[y][xs.clone()].fill(None);
+        }
+    }
+
+    /// Flush the display if it's been set to flush after everything that's
+    /// drawn.
+    fn auto_flush(&mut self) -> Result<(), TerminalError> {
+        if self.auto_flush {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Get the part of `bounding_box` which can be drawn to, once the clip
+    /// area set with [`TerminalDisplay::push_clip`] is taken into account.
+    ///
+    /// [`TerminalDisplay::push_clip`]: crate::TerminalDisplay::push_clip
+    fn clip_area(&self, bounding_box: &Rectangle) -> Rectangle {
+        self.clip
+            .iter()
+            .fold(*bounding_box, |area, clip| self.clamp_area(&area, clip))
+    }
+
+    /// Make a ditherer for drawing rows of `width` pixels, if dithering is
+    /// needed.
+    ///
+    /// This takes the memory from `dither_scratch`, so it should be given back
+    /// with [`TerminalDisplay::finish_dither`] afterwards.
+    fn dither(&mut self, width: usize) -> Option<FloydSteinberg> {
+        match (
+            self.dithering,
+            self.config.threshold(),
+            &self.config.color_mode,
+        ) {
+            (Dithering::None, _, _) | (_, None, ColorMode::TrueColor) => None,
+            (Dithering::FloydSteinberg, threshold, color_mode) => {
+                let mut dither = std::mem::take(&mut self.dither_scratch);
+                dither.reset(color_mode, threshold, width);
+                Some(dither)
             }
         }
-        if self.buffer.len() != height.into() {
-            self.buffer.resize_with(height.into(), || {
-                vec![(Color::BgColor, Color::BgColor); width.into()]
-            })
+    }
+
+    /// Give back the memory of a ditherer made by
+    /// [`TerminalDisplay::dither`], so that it can be reused.
+    fn finish_dither(&mut self, dither: Option<FloydSteinberg>) {
+        if let Some(dither) = dither {
+            self.dither_scratch = dither;
         }
+    }
 
-        Ok((width, height))
+    /// Get the column and row of the cell containing the pixel at `point`
+    /// (which is in drawing coordinates), relative to the top-left of the
+    /// display, or `None` if it's outside the bounds of the display.
+    fn display_cell(&self, point: Point) -> Option<(u16, u16)> {
+        let point = self.to_display(point)?;
+        let bounding_box = self.bounding_box();
+        if !bounding_box.contains(point) {
+            return None;
+        }
+        let point = self.config.orientation.apply(bounding_box.size, point);
+        let cell_size = self.config.cell_size();
+        // We've just checked that the point is within the display, so it's positive; but
+        // a fixed-size display can be bigger than any terminal.
+        let column = (point.x as u32 / cell_size.width).min(u16::MAX.into()) as u16;
+        let row = (point.y as u32 / cell_size.height).min(u16::MAX.into()) as u16;
+        Some((column, row))
+    }
+
+    /// Convert `point` from drawing coordinates to coordinates relative to the
+    /// top-left of the display, or return `None` if it's so far off the display
+    /// that it doesn't fit in those.
+    fn to_display(&self, point: Point) -> Option<Point> {
+        Some(Point::new(
+            point.x.checked_sub(self.offset.x)?,
+            point.y.checked_sub(self.offset.y)?,
+        ))
+    }
+
+    /// Get the part of `area`, which is in drawing coordinates, that's inside
+    /// `bounds`, which is relative to the top-left of the display.
+    ///
+    /// `area` can be anywhere and any size, including so far off the display
+    /// or so big that its corners don't fit in an `i32`; so this is worked out
+    /// in `i64`s, rather than with `Rectangle::intersection`.
+    fn clamp_area(&self, bounds: &Rectangle, area: &Rectangle) -> Rectangle {
+        let clamp = |start: i32, len: u32, offset: i32, bounds_start: i32, bounds_len: u32| {
+            let start = i64::from(start) - i64::from(offset);
+            let end = start + i64::from(len);
+            let bounds_start = i64::from(bounds_start);
+            let bounds_end = bounds_start + i64::from(bounds_len);
+            let clamped_start = start.clamp(bounds_start, bounds_end);
+            let clamped_end = end.clamp(clamped_start, bounds_end);
+            // These are both inside `bounds` now, so they fit.
+            (clamped_start as i32, (clamped_end - clamped_start) as u32)
+        };
+        let (x, width) = clamp(
+            area.top_left.x,
+            area.size.width,
+            self.offset.x,
+            bounds.top_left.x,
+            bounds.size.width,
+        );
+        let (y, height) = clamp(
+            area.top_left.y,
+            area.size.height,
+            self.offset.y,
+            bounds.top_left.y,
+            bounds.size.height,
+        );
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    /// Get how many pixels are cut off the left, right and top of `area`
+    /// (which is in drawing coordinates) by clamping it to `clamped_area`.
+    fn padding(&self, area: &Rectangle, clamped_area: &Rectangle) -> (usize, usize, usize) {
+        let start_x = i64::from(area.top_left.x) - i64::from(self.offset.x);
+        let start_y = i64::from(area.top_left.y) - i64::from(self.offset.y);
+        let clamped_x = i64::from(clamped_area.top_left.x);
+        let left = clamped_x - start_x;
+        let right = (start_x + i64::from(area.size.width))
+            - (clamped_x + i64::from(clamped_area.size.width));
+        let top = i64::from(clamped_area.top_left.y) - start_y;
+        // The clamped area is inside the original area, so these are all positive;
+        // so, if they don't fit in a `usize`, it must be because they're too big.
+        let to_usize = |n: i64| usize::try_from(n).unwrap_or(usize::MAX);
+        (to_usize(left), to_usize(right), to_usize(top))
     }
 
-    fn fill_solid_aligned(
+    /// Draw `color` onto the pixel at `point` in the buffer, if it's inside
+    /// `clip_area`, where `bounding_box` is the bounds of the whole display.
+    fn put_pixel(
         &mut self,
-        columns: Range<u16>,
-        rows: Range<u16>,
+        bounding_box: &Rectangle,
+        clip_area: &Rectangle,
+        point: Point,
         color: Color,
-    ) -> io::Result<()> {
-        // Update the color buffer
-        self.buffer[usize::from(rows.start)..usize::from(rows.end)]
-            .iter_mut()
-            .for_each(|row| {
-                row[usize::from(columns.start)..usize::from(columns.end)].fill((color, color))
-            });
+    ) -> Result<(), TerminalError> {
+        match self.to_display(point) {
+            Some(display_point) if clip_area.contains(display_point) => {
+                // We've just checked that these coordinates fall within the bounds of the
+                // display, so they must be positive.
+                let pixel = &mut self.buffer[display_point.y as usize][display_point.x as usize];
+                *pixel = self.blend_mode.blend(color, *pixel);
+                self.damage_pixels(&Rectangle::new(display_point, Size::new(1, 1)));
+            }
+            display_point => {
+                let on_display = display_point.is_some_and(|point| bounding_box.contains(point));
+                if self.strict && !on_display {
+                    return Err(TerminalError::OutOfBounds { point });
+                }
+            }
+        }
+        Ok(())
+    }
 
-        if color == Color::FgColor {
-            self.stdout
-                .queue(style::SetForegroundColor(CrosstermColor::Reset))?;
+    /// In strict mode, check that all of `area` (in drawing coordinates) is
+    /// inside `bounding_box`.
+    fn check_area(&self, bounding_box: &Rectangle, area: &Rectangle) -> Result<(), TerminalError> {
+        if !self.strict
+            || area.is_zero_sized()
+            || self.clamp_area(bounding_box, area).size == area.size
+        {
+            return Ok(());
+        }
+        // The bounding box always starts at (0, 0), so if the top-left corner of the
+        // area is inside it, the area must be hanging off the bottom or right instead.
+        let top_left_on_display = self
+            .to_display(area.top_left)
+            .is_some_and(|point| bounding_box.contains(point));
+        let point = if top_left_on_display {
+            // The bottom-right corner can be past `i32::MAX`, in which case report
+            // the furthest point we can.
+            let corner = |start: i32, len: u32| {
+                (i64::from(start) + i64::from(len) - 1).min(i32::MAX.into()) as i32
+            };
+            Point::new(
+                corner(area.top_left.x, area.size.width),
+                corner(area.top_left.y, area.size.height),
+            )
         } else {
-            self.stdout
-                .queue(style::SetBackgroundColor(color.to_crossterm_color()))?;
+            area.top_left
+        };
+        Err(TerminalError::OutOfBounds { point })
+    }
+
+    /// Fill `area` of the buffer with `color` blended in using `blend_mode`,
+    /// where `area` is already known to be within the bounds of the display.
+    fn fill_area(&mut self, area: &Rectangle, color: Color, blend_mode: BlendMode) {
+        if color == Color::Transparent {
+            return;
         }
 
-        for row in rows {
-            self.stdout.queue(cursor::MoveTo(columns.start, row))?;
+        self.damage_pixels(area);
 
-            for _ in columns.clone() {
-                if color == Color::FgColor {
-                    self.stdout.write_all("█".as_bytes())?;
-                } else {
-                    self.stdout.write_all(" ".as_bytes())?;
+        // We know these are positive because they have to be within our bounding box.
+        let start_x = area.top_left.x as usize;
+        let end_x = start_x + area.size.width as usize;
+
+        for y in area.rows() {
+            let row = &mut self.buffer[y as usize][start_x..end_x];
+            let translucent = matches!(color, Color::Rgba(_, alpha) if alpha < 255);
+            if blend_mode == BlendMode::Normal && !translucent {
+                // Opaque colors drawn normally don't depend on what's underneath them, so we
+                // can just fill the whole row at once.
+                row.fill(color.blend_over(Color::BgColor));
+            } else {
+                for pixel in row {
+                    *pixel = blend_mode.blend(color, *pixel);
                 }
             }
         }
+    }
 
-        Ok(())
+    /// Get the size of the display in pixels, given the size of the terminal.
+    fn pixel_size(&self, width: u16, height: u16) -> Size {
+        self.fixed_size
+            .unwrap_or_else(|| size(&self.config, width, height))
+    }
+
+    /// Get the bounding box of the display in pixels, given the size of the
+    /// terminal.
+    fn pixel_bounds(&self, width: u16, height: u16) -> Rectangle {
+        Rectangle {
+            top_left: Point::zero(),
+            size: self.pixel_size(width, height),
+        }
+    }
+
+    /// Resize the buffer to the correct size if the terminal's been resized,
+    /// and return the current size of the terminal as (width, height).
+    ///
+    /// If the display has a fixed size, the buffer stays the same size.
+    fn resize(&mut self) -> io::Result<(u16, u16)> {
+        let (width, height) = self.terminal_size()?;
+        self.resize_pending = false;
+        if (width, height) != self.terminal_size {
+            self.terminal_size = (width, height);
+
+            let size = self.pixel_size(width, height);
+            let (pixel_width, pixel_height) = (size.width as usize, size.height as usize);
+            if (pixel_width, pixel_height) != buffer_size(&self.buffer) {
+                self.resize_buffer(pixel_width, pixel_height);
+            }
+
+            // The terminal might reflow its contents when it's resized, so we can't make
+            // any assumptions about what's on screen anymore.
+            self.committed = vec![vec![None; pixel_width]; pixel_height];
+            for text in self.text.values_mut() {
+                text.drawn = false;
+            }
+
+            if let Some(on_resize) = &mut self.on_resize {
+                on_resize(width, height);
+            }
+        }
+
+        Ok((width, height))
+    }
+
+    /// Resize the buffer to `width` by `height` pixels, keeping whatever's cut
+    /// off in `retained`, and bringing back whatever was cut off before.
+    fn resize_buffer(&mut self, width: usize, height: usize) {
+        // First make `retained` big enough for both the old and new buffers, and
+        // copy the old buffer into it.
+        let (old_width, old_height) = buffer_size(&self.buffer);
+        let retained_width = buffer_size(&self.retained).0.max(old_width).max(width);
+        let retained_height = self.retained.len().max(old_height).max(height);
+        for row in &mut self.retained {
+            row.resize(retained_width, Color::BgColor);
+        }
+        self.retained
+            .resize_with(retained_height, || vec![Color::BgColor; retained_width]);
+        for (retained, row) in self.retained.iter_mut().zip(&self.buffer) {
+            retained[..old_width].copy_from_slice(row);
+        }
+
+        // Then take the new buffer back out of it.
+        self.buffer.truncate(height);
+        for (row, retained) in self.buffer.iter_mut().zip(&self.retained) {
+            row.clear();
+            row.extend_from_slice(&retained[..width]);
+        }
+        let rows = self.buffer.len();
+        self.buffer.extend(
+            self.retained[rows..height]
+                .iter()
+                .map(|row| row[..width].to_vec()),
+        );
+
+        // The display's a different shape now, so all of it counts as damaged, and any
+        // damage from before might not even be on it anymore.
+        self.damage = None;
+        self.damage_pixels(&Rectangle::new(
+            Point::zero(),
+            Size::new(width as u32, height as u32),
+        ));
+    }
+
+    /// Get the size of the terminal as (width, height) in cells, only actually
+    /// checking if we have to.
+    fn terminal_size(&self) -> io::Result<(u16, u16)> {
+        if self.poll_size || self.resize_pending {
+            self.size_source.size()
+        } else {
+            Ok(self.terminal_size)
+        }
+    }
+}
+
+/// Whether any of the pixels in the cell at the given column and row of
+/// `buffer` have changed since it was last written to the terminal, according
+/// to `committed`.
+fn cell_changed(
+    config: &RenderConfig,
+    buffer: &[Vec<Color>],
+    committed: &[Vec<Option<Color>>],
+    column: u16,
+    row: u16,
+) -> bool {
+    let (xs, ys) = cell_pixels(config, buffer_size(buffer), column, row);
+    ys.into_iter().any(|y| {
+        buffer[y][xs.clone()]
+            .iter()
+            .zip(&committed[y][xs.clone()])
+            .any(|(&color, &committed)| committed != Some(color))
+    })
+}
+
+/// Get the text attributes of the cell at the given column and row, according
+/// to `attributes`.
+fn cell_attributes(
+    attributes: &HashMap<(u16, u16), Attributes>,
+    column: u16,
+    row: u16,
+) -> Attributes {
+    attributes.get(&(column, row)).copied().unwrap_or_default()
+}
+
+/// Get the colors of the pixels in the cell at the given column and row of
+/// `buffer`, in row-major order, as they should be displayed.
+///
+/// Only the first `width * height` pixels of the cell are used; the rest are
+/// left blank, so that two cells can be compared directly.
+fn cell_contents(
+    config: &RenderConfig,
+    buffer: &[Vec<Color>],
+    column: u16,
+    row: u16,
+) -> [Color; MAX_CELL_PIXELS] {
+    let cell_width = config.render_mode.cell_size().width as usize;
+    let (xs, ys) = cell_pixels(config, buffer_size(buffer), column, row);
+
+    // Any pixels which are past the edge of the buffer are left blank.
+    let mut pixels = [config.display_color(Color::BgColor); MAX_CELL_PIXELS];
+    for dy in 0..ys.len() {
+        let y = if config.orientation.flip_y() {
+            ys.end - 1 - dy
+        } else {
+            ys.start + dy
+        };
+        let row = &buffer[y][xs.clone()];
+        for dx in 0..row.len() {
+            let color = if config.orientation.flip_x() {
+                row[row.len() - 1 - dx]
+            } else {
+                row[dx]
+            };
+            pixels[dy * cell_width + dx] = config.display_color(config.pixel_color(color));
+        }
+    }
+    pixels
+}
+
+/// Clear the terminal, and write a message in the middle of it saying that
+/// it's `have` (columns, rows) in size, but needs to be at least `need`.
+fn write_too_small(
+    out: &mut TerminalWriter<impl Write>,
+    config: &RenderConfig,
+    have: (u16, u16),
+    need: (u16, u16),
+) -> io::Result<()> {
+    let (columns, rows) = have;
+    let lines = [
+        "Terminal too small".to_string(),
+        format!("{}x{} (need {}x{})", have.0, have.1, need.0, need.1),
+    ];
+    out.start_update()?;
+    out.clear_screen()?;
+    let top = rows.saturating_sub(lines.len() as u16) / 2;
+    for (row, line) in (top..rows).zip(&lines) {
+        let len = line.chars().count().min(columns.into()) as u16;
+        out.move_to((columns - len) / 2, row)?;
+        for character in line.chars().take(len.into()) {
+            out.write_text(config, character, Color::FgColor, Color::BgColor)?;
+        }
+    }
+    out.finish_update()?;
+    out.flush()
+}
+
+/// Draw the given rows of cells of `buffer` to `out` as a sixel image, for a
+/// display whose top-left corner is at `origin` on the terminal.
+///
+/// Only the first `columns` columns of cells are drawn. `default_colors` are the
+/// RGB values to use for the terminal's default (background, foreground)
+/// colors.
+#[cfg(feature = "sixel")]
+fn write_sixel_rows(
+    out: &mut TerminalWriter<impl Write>,
+    config: &RenderConfig,
+    buffer: &[Vec<Color>],
+    default_colors: (Rgb888, Rgb888),
+    (origin_x, origin_y): (u16, u16),
+    columns: u16,
+    rows: Range<u16>,
+) -> io::Result<()> {
+    let cell_size = config.cell_size();
+    let (width, height) = buffer_size(buffer);
+    let size = Size::new(width as u32, height as u32);
+    let image_width = (usize::from(columns) * cell_size.width as usize).min(width);
+    let top = (usize::from(rows.start) * cell_size.height as usize).min(height);
+    let bottom = (usize::from(rows.end) * cell_size.height as usize).min(height);
+
+    out.move_to(origin_x, origin_y.saturating_add(rows.start))?;
+    out.write_sixel(image_width, bottom - top, |x, y| {
+        // These are inside the buffer, which is far too small for them to overflow.
+        let point = config
+            .orientation
+            .apply(size, Point::new(x as i32, (top + y) as i32));
+        config.display_rgb(buffer[point.y as usize][point.x as usize], default_colors)
+    })
+}
+
+// Make sure `TerminalDisplay` stays `Send`, since it's documented as such.
+const _: () = {
+    fn assert_send<T: Send>() {}
+    // This gets checked for any `W`, not just the one it's used with below.
+    fn assert_display_send<W: Write + Send>() {
+        assert_send::<TerminalDisplay<W>>();
+    }
+    let _ = assert_display_send::<Stdout>;
+};
+
+impl<W: Write> Drop for TerminalDisplay<W> {
+    fn drop(&mut self) {
+        // We can't do anything about errors here, but we should still try our best to
+        // show what was drawn and clean up.
+        if self.stdout.has_writer() {
+            let _ = self.flush();
+        }
+        let _ = self.restore();
+        #[cfg(feature = "image")]
+        let _ = self.finish_gif();
     }
 }
 
-impl OriginDimensions for TerminalDisplay {
+impl<W: Write> OriginDimensions for TerminalDisplay<W> {
     fn size(&self) -> Size {
-        let (width, height) = terminal::size().expect("failed to get terminal size");
-        size(width, height)
+        if let Some(size) = self.fixed_size {
+            return size;
+        }
+        // This can't return an error, so if the terminal can't be queried anymore (e.g.
+        // because it's been detached), fall back to the last size we knew about.
+        // Everything else treats a terminal with no rows or columns as having nothing
+        // to draw, so the size being zero is fine too.
+        let (width, height) = self.terminal_size().unwrap_or(self.terminal_size);
+        size(&self.config, width, height)
     }
 }
 
-impl DrawTarget for TerminalDisplay {
+/// embedded_graphics' adapters ([`translated`], [`cropped`], [`clipped`] and
+/// [`color_converted`]) all pass [`fill_contiguous`] and [`fill_solid`]
+/// through to the display with the area adjusted, rather than breaking them up
+/// into pixels, so filling rectangles through them is just as fast as filling
+/// them directly. [`translated`] in particular is the same as
+/// [`TerminalDisplay::set_offset`], just scoped to whatever's drawn through it.
+///
+/// [`translated`]: https://docs.rs/embedded-graphics/0.7/embedded_graphics/draw_target/trait.DrawTargetExt.html#tymethod.translated
+/// [`cropped`]: https://docs.rs/embedded-graphics/0.7/embedded_graphics/draw_target/trait.DrawTargetExt.html#tymethod.cropped
+/// [`clipped`]: https://docs.rs/embedded-graphics/0.7/embedded_graphics/draw_target/trait.DrawTargetExt.html#tymethod.clipped
+/// [`color_converted`]: https://docs.rs/embedded-graphics/0.7/embedded_graphics/draw_target/trait.DrawTargetExt.html#tymethod.color_converted
+/// [`fill_contiguous`]: DrawTarget::fill_contiguous
+/// [`fill_solid`]: DrawTarget::fill_solid
+impl<W: Write> DrawTarget for TerminalDisplay<W> {
     type Color = Color;
 
-    type Error = io::Error;
+    type Error = TerminalError;
 
-    fn draw_iter<I>(&mut self, pixels: I) -> io::Result<()>
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), TerminalError>
     where
         I: IntoIterator<Item = Pixel<Color>>,
     {
         let (width, height) = self.resize()?;
-        let bounding_box = bounding_box(width, height);
+        let bounding_box = self.pixel_bounds(width, height);
+        let clip_area = self.clip_area(&bounding_box);
 
         for Pixel(point, color) in pixels {
-            if bounding_box.contains(point) {
-                // We've just checked that these coordinates fall within the bounds of the
-                // terminal, so they must fit within a u16.
-                let column = point.x as u16;
-                let row = (point.y / 2) as u16;
-                self.stdout.queue(cursor::MoveTo(column, row))?;
-
-                let (top_color, bottom_color) =
-                    &mut self.buffer[usize::from(row)][usize::from(column)];
-                if point.y % 2 == 0 {
-                    *top_color = color;
-                } else {
-                    *bottom_color = color;
-                }
-                write_cell(&mut self.stdout, *top_color, *bottom_color)?;
-            }
+            self.put_pixel(&bounding_box, &clip_area, point, color)?;
         }
-        Ok(())
+        self.auto_flush()
     }
 
-    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> io::Result<()>
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), TerminalError>
     where
         I: IntoIterator<Item = Color>,
     {
         let (width, height) = self.resize()?;
-        let bounding_box = bounding_box(width, height);
-
-        // Clamp the passed area to the size of the terminal.
-        let clamped_area = bounding_box.intersection(area);
-
-        // Compute all of the dimensions we need.
-        let (left_padding, right_padding, top_padding, start_y, end_y) =
-            match (area.bottom_right(), clamped_area.bottom_right()) {
-                (Some(bottom_right), Some(clamped_bottom_right)) => {
-                    // The clamped area will only ever be the same size or smaller than the original
-                    // area, which means the results of these subtractions will
-                    // always be positive; so, if they don't fit in a `usize`,
-                    // it must be because they're too big, so saturate them to `usize::MAX`.
-                    let left_padding = usize::try_from(clamped_area.top_left.x - area.top_left.x)
-                        .unwrap_or(usize::MAX);
-                    let right_padding = usize::try_from(bottom_right.x - clamped_bottom_right.x)
-                        .unwrap_or(usize::MAX);
-                    let top_padding = usize::try_from(clamped_area.top_left.y - area.top_left.y)
-                        .unwrap_or(usize::MAX);
-
-                    let start_y = clamped_area.top_left.y;
-                    let end_y = clamped_bottom_right.y;
-
-                    (left_padding, right_padding, top_padding, start_y, end_y)
+        let bounding_box = self.pixel_bounds(width, height);
+
+        // Clamp the passed area to the size of the display.
+        self.check_area(&bounding_box, area)?;
+        let clamped_area = self.clamp_area(&self.clip_area(&bounding_box), area);
+        // If the clamped area is zero-sized, we've got nothing to draw.
+        if clamped_area.is_zero_sized() {
+            return Ok(());
+        }
+
+        // Compute all of the dimensions we need. These are all relative to the
+        // unclamped area, so they work the same way however far it hangs off any edge
+        // of the display; and since the buffer stores every pixel separately, it
+        // doesn't matter which row of a cell the area starts on.
+        let (left_padding, right_padding, top_padding) = self.padding(area, &clamped_area);
+
+        // We know these are positive because they have to be within our bounding box.
+        let start_x = clamped_area.top_left.x as usize;
+        let end_x = start_x + clamped_area.size.width as usize;
+
+        // Dithering has to happen here rather than when flushing, since it relies on
+        // knowing which pixels are next to each other in the image being drawn, which
+        // isn't necessarily the case for what's already in the buffer.
+        self.damage_pixels(&clamped_area);
+        let mut dither = self.dither(end_x - start_x);
+        let blend_mode = self.blend_mode;
+
+        // Everything that's out of bounds between one visible pixel and the next gets
+        // skipped in one go, by getting the next visible pixel's color with `nth`:
+        // first the rows above the display and the start of the first visible row, and
+        // then the end of each row and the start of the next. Nothing past the end of
+        // the last visible row gets skipped at all. Iterators which know how to get to
+        // an item without producing the ones before it (like slice iterators) can do
+        // this cheaply; other iterators still have to produce every color they skip.
+        let mut colors = colors.into_iter();
+        let mut skip = usize::try_from(area.size.width)
+            .unwrap_or(usize::MAX)
+            .saturating_mul(top_padding)
+            .saturating_add(left_padding);
+        'rows: for y in clamped_area.rows() {
+            for (i, pixel) in self.buffer[y as usize][start_x..end_x]
+                .iter_mut()
+                .enumerate()
+            {
+                let color = match colors.nth(std::mem::take(&mut skip)) {
+                    Some(color) => color,
+                    // We've run out of colors, so there's nothing left to draw.
+                    None => break 'rows,
+                };
+                // Transparent pixels leave what's already there exactly as it is, without
+                // even dithering it again.
+                if color == Color::Transparent {
+                    continue;
                 }
-                // If either of those boxes is zero-sized (which causes `bottom_right` to return
-                // `None`), we've got nothing to draw.
-                _ => return Ok(()),
+                let color = blend_mode.blend(color, *pixel);
+                *pixel = match &mut dither {
+                    Some(dither) => dither.quantize(i, color),
+                    None => color,
+                };
+            }
+
+            if let Some(dither) = &mut dither {
+                dither.next_row();
+            }
+            skip = right_padding.saturating_add(left_padding);
+        }
+        self.finish_dither(dither);
+        self.auto_flush()
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Color) -> Result<(), TerminalError> {
+        self.clear_region(area, color)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), TerminalError> {
+        let (width, height) = self.resize()?;
+        let bounding_box = self.pixel_bounds(width, height);
+        self.fill_area(&self.clip_area(&bounding_box), color, BlendMode::Normal);
+        // Anything that's been cut off by the terminal shrinking should be cleared too,
+        // unless it's outside the area being cleared anyway.
+        if self.clip.is_empty() {
+            self.retained = Vec::new();
+        }
+        // Clearing is expected to get rid of anything else that was on the screen, even
+        // the parts we've never drawn to, so redraw everything.
+        self.invalidate();
+        self.auto_flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use embedded_graphics::draw_target::DrawTargetExt;
+    use embedded_graphics_core::pixelcolor::Rgb888;
+    use embedded_graphics_core::prelude::*;
+    use embedded_graphics_core::primitives::Rectangle;
+
+    use crate::test_util;
+    use crate::tty::SizeSource;
+    use crate::{
+        Color, ColorMode, Dithering, Fit, Orientation, RenderMode, TerminalDisplay, TerminalError,
+    };
+
+    #[test]
+    fn fill_contiguous_off_top_left() {
+        let (mut display, _) = test_util::display(TerminalDisplay::builder(), 4, 3);
+        let area = Rectangle::new(Point::new(-3, -5), Size::new(6, 9));
+        // Give every pixel of the area a different color, so that anything ending up in
+        // the wrong place shows.
+        let color = |x: i32, y: i32| Color::Rgb(Rgb888::new(x as u8, y as u8, 0));
+        let colors = area.points().map(|point| color(point.x + 3, point.y + 5));
+        display.fill_contiguous(&area, colors).unwrap();
+
+        for point in display.bounding_box().points() {
+            let expected = if area.contains(point) {
+                color(point.x + 3, point.y + 5)
+            } else {
+                Color::BgColor
+            };
+            assert_eq!(display.get_pixel(point), Some(expected), "{point:?}");
+        }
+    }
+
+    #[test]
+    fn fill_keeps_other_half() {
+        let (mut display, out) = test_util::display(TerminalDisplay::builder(), 1, 1);
+        display.set_pixel(Point::new(0, 0), Color::Red).unwrap();
+        display.present().unwrap();
+        out.take();
+
+        display
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 1), Size::new(1, 1)),
+                Color::Blue,
+            )
+            .unwrap();
+        assert_eq!(display.get_pixel(Point::new(0, 0)), Some(Color::Red));
+        assert_eq!(display.get_pixel(Point::new(0, 1)), Some(Color::Blue));
+        display.present().unwrap();
+        // The bottom half is drawn in blue over the red top half.
+        assert_eq!(out.take(), "\x1b[1;1H\x1b[38;5;12m\x1b[48;5;9m▄\x1b[1;1H");
+    }
+
+    #[test]
+    fn strict_off_screen_pixel() {
+        let pixels = [
+            Pixel(Point::new(0, 0), Color::Red),
+            Pixel(Point::new(5, 0), Color::Red),
+            Pixel(Point::new(1, 0), Color::Red),
+        ];
+
+        let (mut display, _) = test_util::display(TerminalDisplay::builder(), 2, 1);
+        display.draw_iter(pixels).unwrap();
+        assert_eq!(display.get_pixel(Point::new(0, 0)), Some(Color::Red));
+        assert_eq!(display.get_pixel(Point::new(1, 0)), Some(Color::Red));
+
+        let (mut display, _) = test_util::display(TerminalDisplay::builder().strict(true), 2, 1);
+        let error = display.draw_iter(pixels).unwrap_err();
+        assert!(matches!(error, TerminalError::OutOfBounds { point } if point == Point::new(5, 0)));
+        // Only the pixels before the off-screen one get drawn.
+        assert_eq!(display.get_pixel(Point::new(0, 0)), Some(Color::Red));
+        assert_eq!(display.get_pixel(Point::new(1, 0)), Some(Color::BgColor));
+    }
+
+    /// A draw target which records which of its methods get called, and passes
+    /// them on to a display.
+    struct Recording<'a> {
+        display: &'a mut TerminalDisplay<test_util::SharedWriter>,
+        calls: Vec<&'static str>,
+    }
+
+    impl Dimensions for Recording<'_> {
+        fn bounding_box(&self) -> Rectangle {
+            self.display.bounding_box()
+        }
+    }
+
+    impl DrawTarget for Recording<'_> {
+        type Color = Color;
+        type Error = TerminalError;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            self.calls.push("draw_iter");
+            self.display.draw_iter(pixels)
+        }
+
+        fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Self::Color>,
+        {
+            self.calls.push("fill_contiguous");
+            self.display.fill_contiguous(area, colors)
+        }
+
+        fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+            self.calls.push("fill_solid");
+            self.display.fill_solid(area, color)
+        }
+    }
+
+    #[test]
+    fn adapters_keep_fills() {
+        let (mut display, _) = test_util::display(TerminalDisplay::builder(), 4, 2);
+        let mut target = Recording {
+            display: &mut display,
+            calls: Vec::new(),
+        };
+        let area = Rectangle::new(Point::zero(), Size::new(2, 2));
+        target
+            .translated(Point::new(1, 1))
+            .fill_solid(&area, Color::Red)
+            .unwrap();
+        target
+            .translated(Point::new(2, 2))
+            .fill_contiguous(&area, [Color::Blue; 4])
+            .unwrap();
+        target
+            .cropped(&Rectangle::new(Point::new(0, 2), Size::new(2, 2)))
+            .fill_contiguous(&area, [Color::Green; 4])
+            .unwrap();
+        target
+            .clipped(&Rectangle::new(Point::zero(), Size::new(1, 1)))
+            .fill_solid(&area, Color::Yellow)
+            .unwrap();
+        target
+            .color_converted()
+            .fill_solid(
+                &Rectangle::new(Point::new(3, 0), Size::new(1, 1)),
+                Rgb888::WHITE,
+            )
+            .unwrap();
+        assert_eq!(
+            target.calls,
+            [
+                "fill_solid",
+                "fill_contiguous",
+                "fill_contiguous",
+                "fill_solid",
+                "fill_solid"
+            ]
+        );
+
+        let expected = [
+            [
+                Color::Yellow,
+                Color::BgColor,
+                Color::BgColor,
+                Color::Rgb(Rgb888::WHITE),
+            ],
+            [Color::BgColor, Color::Red, Color::Red, Color::BgColor],
+            [Color::Green, Color::Green, Color::Blue, Color::Blue],
+            [Color::Green, Color::Green, Color::Blue, Color::Blue],
+        ];
+        for (y, row) in expected.into_iter().enumerate() {
+            for (x, color) in row.into_iter().enumerate() {
+                let point = Point::new(x as i32, y as i32);
+                assert_eq!(display.get_pixel(point), Some(color), "{point:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn zero_size_terminal() {
+        for (columns, rows) in [(0, 0), (0, 7), (7, 0)] {
+            let (mut display, out) = test_util::display(TerminalDisplay::builder(), columns, rows);
+            assert_eq!(display.size().width * display.size().height, 0);
+            display.set_pixel(Point::new(0, 0), Color::Red).unwrap();
+            display
+                .fill_solid(&Rectangle::new(Point::zero(), Size::new(4, 4)), Color::Blue)
+                .unwrap();
+            display.clear(Color::Green).unwrap();
+            display.present().unwrap();
+            assert_eq!(display.get_pixel(Point::new(0, 0)), None);
+            // Nothing gets drawn; the cursor just gets parked like usual.
+            assert_eq!(out.take(), format!("\x1b[{};1H", rows.max(1)));
+        }
+    }
+
+    #[test]
+    fn clipped_fill() {
+        let (mut display, _) = test_util::display(TerminalDisplay::builder(), 6, 3);
+        let area = display.bounding_box();
+        display.clear(Color::Green).unwrap();
+
+        display.push_clip(Rectangle::new(Point::new(1, 1), Size::new(4, 3)));
+        display.push_clip(Rectangle::new(Point::new(2, 0), Size::new(4, 3)));
+        display.fill_solid(&area, Color::Red).unwrap();
+        // Only the intersection of the two clip areas gets filled.
+        let inside = Rectangle::new(Point::new(2, 1), Size::new(3, 2));
+        for point in area.points() {
+            let expected = if inside.contains(point) {
+                Color::Red
+            } else {
+                Color::Green
             };
+            assert_eq!(display.get_pixel(point), Some(expected), "{point:?}");
+        }
 
-        let mut colors = colors
-            .into_iter()
-            .skip(area.size.width.try_into().unwrap_or(usize::MAX) * top_padding);
+        display.pop_clip();
+        display.pop_clip();
+        display.fill_solid(&area, Color::Blue).unwrap();
+        assert!(area
+            .points()
+            .all(|point| display.get_pixel(point) == Some(Color::Blue)));
+    }
 
-        // TODO: replace with `Iterator::advance_by` once it's stabilised.
-        fn advance_by<T>(iterator: &mut impl Iterator<Item = T>, n: usize) {
-            if let Some(i) = n.checked_sub(1) {
-                let _ = iterator.nth(i);
+    #[test]
+    fn extreme_coordinates() {
+        let (mut display, _) = test_util::display(TerminalDisplay::builder(), 4, 3);
+        let corners = [
+            Point::new(i32::MIN, i32::MIN),
+            Point::new(i32::MIN, i32::MAX),
+            Point::new(i32::MAX, i32::MIN),
+            Point::new(i32::MAX, i32::MAX),
+            Point::new(-1, -1),
+        ];
+        let sizes = [
+            Size::new(u32::MAX, u32::MAX),
+            Size::new(u32::MAX, 1),
+            Size::new(1, u32::MAX),
+        ];
+        for top_left in corners {
+            for size in sizes {
+                let area = Rectangle::new(top_left, size);
+                display.fill_solid(&area, Color::Red).unwrap();
+                display
+                    .fill_contiguous(&area, std::iter::repeat(Color::Blue))
+                    .unwrap();
+                display
+                    .fill_checker(&area, Color::Red, Color::Blue, u32::MAX)
+                    .unwrap();
+                display.invalidate_region(&area);
             }
+            display.draw_iter([Pixel(top_left, Color::Green)]).unwrap();
+            display.set_pixel(top_left, Color::Green).unwrap();
+            display.blit(top_left, &[Color::Green; 4], 2).unwrap();
+            display
+                .draw_line_aa(top_left, Point::new(1, 1), Color::Green)
+                .unwrap();
+            display.present().unwrap();
         }
+    }
 
-        for y in clamped_area.rows() {
-            let is_top_half = y % 2 == 0;
+    #[test]
+    fn braille_threshold_gradient() {
+        let builder = TerminalDisplay::builder()
+            .render_mode(RenderMode::Braille)
+            .braille_threshold(Some(0.3));
+        let (mut display, out) = test_util::display(builder, 4, 1);
+        let row = Rectangle::new(Point::zero(), Size::new(8, 1));
+        let gradient = (0..8).map(|x| Color::Rgb(Rgb888::new(x * 36, x * 36, x * 36)));
+        display.fill_contiguous(&row, gradient).unwrap();
+        display.present().unwrap();
+        // Luminance passes 0.3 between 144 and 180, so the right dot of the third cell
+        // and both dots of the fourth are on.
+        assert_eq!(
+            out.take(),
+            "\x1b[1;1H\x1b[49m  \x1b[38;2;180;180;180m⠈\x1b[38;2;216;216;216m⠉\x1b[1;1H"
+        );
+    }
 
-            // Move the cursor to the start of the row.
-            // We know these will fit in `u16`s because they have to be within
-            // our bounding box of the terminal.
-            let column = clamped_area.top_left.x as u16;
-            let row = (y / 2) as u16;
-            self.stdout.queue(cursor::MoveTo(column, row))?;
+    #[test]
+    fn steady_state_frames_dont_allocate() {
+        let builder = TerminalDisplay::builder()
+            .color_mode(ColorMode::Ansi256)
+            .dithering(Dithering::FloydSteinberg);
+        let mut display = builder
+            .build_with(io::sink(), SizeSource::Fixed(20, 10))
+            .unwrap();
+        let image: Vec<Color> = (0..7 * 5)
+            .map(|i| Color::Rgb(Rgb888::new(i * 7, 255 - i * 7, 128)))
+            .collect();
 
-            // Skip the out-of-bounds part at the start of this row.
-            advance_by(&mut colors, left_padding);
+        let frame = |display: &mut TerminalDisplay<io::Sink>, t: u8| {
+            let area = display.bounding_box();
+            let colors = area
+                .points()
+                .map(|point| Color::Rgb(Rgb888::new(point.x as u8 * 10, point.y as u8 * 10, t)));
+            display.fill_contiguous(&area, colors).unwrap();
+            display
+                .blit_scaled(&image, Size::new(7, 5), Fit::Contain)
+                .unwrap();
+            display.present().unwrap();
+        };
+        // The first frames are allowed to allocate the scratch memory.
+        frame(&mut display, 0);
+        frame(&mut display, 1);
+        let before = test_util::allocations();
+        for t in 2..10 {
+            frame(&mut display, t * 20);
+        }
+        assert_eq!(test_util::allocations() - before, 0);
+    }
 
-            for x in clamped_area.columns() {
-                let column = x as u16;
+    #[test]
+    fn corner_orientations() {
+        // The top-left pixel should land in the top or bottom half of the mirrored
+        // corner cell.
+        for (orientation, expected) in [
+            (Orientation::Normal, "\x1b[1;1H\x1b[38;5;9m\x1b[49m▀"),
+            (Orientation::FlipH, "\x1b[1;2H\x1b[38;5;9m\x1b[49m▀"),
+            (Orientation::FlipV, "\x1b[2;1H\x1b[38;5;9m\x1b[49m▄"),
+            (Orientation::Rotate180, "\x1b[2;2H\x1b[38;5;9m\x1b[49m▄"),
+        ] {
+            let builder = TerminalDisplay::builder().orientation(orientation);
+            let (mut display, out) = test_util::display(builder, 2, 2);
+            display.set_pixel(Point::zero(), Color::Red).unwrap();
+            display.present().unwrap();
+            assert_eq!(
+                out.take(),
+                format!("{expected}\x1b[2;1H"),
+                "{orientation:?}"
+            );
+            // The drawing coordinates stay the same.
+            assert_eq!(display.get_pixel(Point::zero()), Some(Color::Red));
+        }
+    }
 
-                let color = colors.next();
+    #[test]
+    fn odd_fixed_height() {
+        for width in 1..=5 {
+            for height in 1..=7 {
+                let builder = TerminalDisplay::builder().fixed_size(width, height);
+                let (mut display, out) = test_util::display(builder, 10, 10);
+                let area = display.bounding_box();
+                display.fill_solid(&area, Color::Red).unwrap();
+                display.present().unwrap();
+                assert!(area
+                    .points()
+                    .all(|point| display.get_pixel(point) == Some(Color::Red)));
 
-                let (top_color, bottom_color) =
-                    &mut self.buffer[usize::from(row)][usize::from(column)];
+                let output = out.take();
+                let full_rows = height / 2;
+                assert_eq!(
+                    output.matches('▄').count() as u32,
+                    width * full_rows,
+                    "{width}x{height}"
+                );
+                // The last row of cells only has a top half if the height is odd.
+                assert_eq!(
+                    output.matches('▀').count() as u32,
+                    width * (height % 2),
+                    "{width}x{height}"
+                );
 
-                if let Some(color) = color {
-                    if is_top_half {
-                        *top_color = color;
+                let pattern = |i: u32| {
+                    if i.is_multiple_of(2) {
+                        Color::Blue
                     } else {
-                        *bottom_color = color;
+                        Color::Green
                     }
-                } else if is_top_half || y == start_y {
-                    // Return early, as long as we don't still need to draw for the sake of the top
-                    // half.
-                    return Ok(());
-                }
-
-                // Wait until the bottom half of the cell to write it, unless this is the last
-                // row and there won't be a bottom half. Our main bottleneck is actually writing
-                // to the tty, so the less we write the better.
-                if !is_top_half || y == end_y {
-                    write_cell(&mut self.stdout, *top_color, *bottom_color)?;
+                };
+                display
+                    .fill_contiguous(&area, (0..width * height).map(pattern))
+                    .unwrap();
+                display.present().unwrap();
+                for point in area.points() {
+                    let i = point.y as u32 * width + point.x as u32;
+                    assert_eq!(
+                        display.get_pixel(point),
+                        Some(pattern(i)),
+                        "{width}x{height}"
+                    );
                 }
             }
-
-            // Now skip the out-of-bounds part at the end of this row.
-            advance_by(&mut colors, right_padding);
         }
-        Ok(())
     }
 
-    fn fill_solid(&mut self, area: &Rectangle, color: Color) -> io::Result<()> {
-        let (width, height) = self.resize()?;
-        let bounding_box = bounding_box(width, height);
+    #[test]
+    fn brightness() {
+        let (mut display, out) = test_util::display(TerminalDisplay::builder(), 1, 1);
+        let color = Color::Rgb(Rgb888::new(200, 100, 50));
+        display.set_pixel(Point::new(0, 0), color).unwrap();
+        display.set_pixel(Point::new(0, 1), Color::Red).unwrap();
+
+        // The RGB color's channels get halved, but the named color is left alone.
+        display.set_brightness(0.5);
+        display.present().unwrap();
+        assert_eq!(
+            out.take(),
+            "\x1b[1;1H\x1b[38;5;9m\x1b[48;2;100;50;25m▄\x1b[1;1H"
+        );
+        display.set_brightness(0.0);
+        display.present().unwrap();
+        assert_eq!(
+            out.take(),
+            "\x1b[1;1H\x1b[38;5;9m\x1b[48;2;0;0;0m▄\x1b[1;1H"
+        );
+        assert_eq!(display.get_pixel(Point::new(0, 0)), Some(color));
+    }
 
-        // Clamp the passed area to the size of the terminal.
-        let clamped_area = bounding_box.intersection(area);
+    #[test]
+    fn rle_matches_expanded() {
+        let (t, r, b) = (Color::Transparent, Color::Red, Color::Blue);
+        // A 4x3 sprite, with runs that wrap onto the next row.
+        let runs = [(t, 1), (r, 2), (t, 2), (b, 6), (r, 1), (t, 2)];
+        let expanded = [t, r, r, t, t, b, b, b, b, b, b, r, t, t];
 
-        let top_left = clamped_area.top_left;
-        let bottom_right = match clamped_area.bottom_right() {
-            Some(bottom_right) => bottom_right,
-            // If the box is zero sized, we don't need to draw anything.
-            _ => return Ok(()),
+        let draw = |blit: &dyn Fn(&mut TerminalDisplay<test_util::SharedWriter>, Point)| {
+            let (mut display, _) = test_util::display(TerminalDisplay::builder(), 5, 2);
+            display.clear(Color::Green).unwrap();
+            blit(&mut display, Point::new(-1, 1));
+            let area = display.bounding_box();
+            area.points()
+                .map(|point| display.get_pixel(point).unwrap())
+                .collect::<Vec<_>>()
         };
+        let decoded = draw(&|display, top_left| display.blit_rle(top_left, &runs, 4).unwrap());
+        let blitted =
+            draw(&|display, top_left| display.blit(top_left, &expanded[..12], 4).unwrap());
+        assert_eq!(decoded, blitted);
+        // The transparent pixels are left alone.
+        assert_eq!(decoded[5 + 2], Color::Green);
+        assert_eq!(decoded[5 * 2], Color::Blue);
+    }
 
-        let start_column = top_left.x as u16;
-        let end_column = start_column + clamped_area.size.width as u16;
+    #[test]
+    fn clear_restores_empty_glyph() {
+        let builder = TerminalDisplay::builder().empty_cell('·', Color::DarkGrey);
+        let (mut display, out) = test_util::display(builder, 3, 1);
+        display.clear(Color::BgColor).unwrap();
+        display.set_pixel(Point::new(1, 0), Color::Red).unwrap();
+        display.present().unwrap();
+        assert_eq!(
+            out.take(),
+            "\x1b[1;1H\x1b[38;5;8m\x1b[49m·\x1b[38;5;9m▀\x1b[38;5;8m·\x1b[1;1H"
+        );
 
-        if top_left.y % 2 == 1 {
-            // We need to draw the first row normally, since we still need to change the
-            // color of the top half as we go.
-            let row = (top_left.y / 2) as u16;
-            self.stdout.queue(cursor::MoveTo(start_column, row))?;
+        // Clearing the pixel brings back the empty glyph under it.
+        display.set_pixel(Point::new(1, 0), Color::BgColor).unwrap();
+        display.present().unwrap();
+        assert_eq!(out.take(), "\x1b[1;2H\x1b[38;5;8m\x1b[49m·\x1b[1;1H");
 
-            for (top_color, bottom_color) in &mut self.buffer[usize::from(row)]
-                [usize::from(start_column)..usize::from(end_column)]
-            {
-                *bottom_color = color;
-                write_cell(&mut self.stdout, *top_color, *bottom_color)?;
-            }
-        }
+        display.set_pixel(Point::new(1, 0), Color::Red).unwrap();
+        display.clear(Color::BgColor).unwrap();
+        display.present().unwrap();
+        assert!(out.take().contains("\x1b[38;5;8m\x1b[49m···"));
+    }
+
+    #[test]
+    fn shallow_aa_line_coverage() {
+        let (mut display, _) = test_util::display(TerminalDisplay::builder(), 5, 2);
+        let black = Color::Rgb(Rgb888::BLACK);
+        display.clear(black).unwrap();
+        display
+            .draw_line_aa(
+                Point::new(0, 0),
+                Point::new(4, 1),
+                Color::Rgb(Rgb888::WHITE),
+            )
+            .unwrap();
 
-        // Figure out the start and end row of the solidly-filled part.
-        let mut start_row = (top_left.y / 2) as u16;
-        if top_left.y % 2 == 1 {
-            // If we start in the second half of a row, that row gets filled normally; so start the solidly-filled part one row later.
-            start_row += 1;
+        // The line drops by a quarter of a pixel each column, so the coverage moves
+        // from the top row to the bottom row a quarter at a time.
+        let coverage = |alpha| Color::Rgba(Rgb888::WHITE, alpha).blend_over(black);
+        let top = [255, 191, 128, 64, 0];
+        let bottom = [0, 64, 128, 191, 255];
+        for x in 0..5 {
+            assert_eq!(
+                display.get_pixel(Point::new(x, 0)),
+                Some(coverage(top[x as usize])),
+                "({x}, 0)"
+            );
+            assert_eq!(
+                display.get_pixel(Point::new(x, 1)),
+                Some(coverage(bottom[x as usize])),
+                "({x}, 1)"
+            );
+            assert_eq!(display.get_pixel(Point::new(x, 2)), Some(black));
         }
+    }
+
+    #[test]
+    fn restore_cursor_visibility() {
+        const SHOW: &str = "\x1b[?25h";
+        const HIDE: &str = "\x1b[?25l";
 
-        // We're building an exclusive range, so the end point is one after the last row.
-        let mut end_row = (bottom_right.y / 2) as u16 + 1;
-        if bottom_right.y % 2 == 0 {
-            // If we're ending on the top half of a row, that row gets filled normally; so end the solidly-filled part one row earlier.
-            end_row -= 1;
+        // The builder hiding the cursor gets undone.
+        let builder = TerminalDisplay::builder().hide_cursor(true);
+        let (mut display, out) = test_util::display(builder, 1, 1);
+        display.restore().unwrap();
+        assert!(out.take().contains(SHOW));
+
+        // Asking for it to be hidden as well doesn't write anything, but does mean
+        // it's left hidden.
+        let builder = TerminalDisplay::builder().hide_cursor(true);
+        let (mut display, out) = test_util::display(builder, 1, 1);
+        display.set_cursor_visible(false).unwrap();
+        assert_eq!(out.take(), "");
+        display.restore().unwrap();
+        assert!(!out.take().contains(SHOW));
+
+        let (mut display, out) = test_util::display(TerminalDisplay::builder(), 1, 1);
+        display.hide_cursor().unwrap();
+        assert_eq!(out.take(), HIDE);
+        // Other programs get the cursor back while the display's suspended.
+        display.suspend().unwrap();
+        assert!(out.take().contains(SHOW));
+        display.resume().unwrap();
+        assert!(out.take().contains(HIDE));
+        display.restore().unwrap();
+        assert!(!out.take().contains(SHOW));
+    }
+
+    #[test]
+    fn ansi_string_moves_after_text() {
+        let builder = TerminalDisplay::builder().assume_narrow_blocks(false);
+        let (mut display, _) = test_util::display(builder, 3, 1);
+        display.clear(Color::Red).unwrap();
+        display
+            .put_str(0, 0, "x", Color::FgColor, Color::BgColor)
+            .unwrap();
+        // The text could be wider than it's meant to be, so the cursor gets moved to
+        // where the next cell should be afterwards.
+        assert_eq!(
+            display.to_ansi_string(),
+            "\x1b[1;1H\x1b[39m\x1b[49mx\x1b[1;2H\x1b[38;5;9m\x1b[48;5;9m▄\x1b[1;3H▄"
+        );
+    }
+
+    #[test]
+    fn fill_contiguous_skips_with_nth() {
+        /// Gives each pixel's index as its color, counting how many colors it
+        /// actually produces.
+        struct Indices {
+            index: u8,
+            produced: usize,
         }
 
-        self.fill_solid_aligned(start_column..end_column, start_row..end_row, color)?;
+        impl Iterator for Indices {
+            type Item = Color;
 
-        if bottom_right.y % 2 == 0 {
-            // We need to draw the last row normally, since we still need to
-            // change the color of the bottom half as we go.
-            let row = (bottom_right.y / 2) as u16;
-            self.stdout.queue(cursor::MoveTo(start_column, row))?;
-            for (top_color, bottom_color) in &mut self.buffer[usize::from(row)]
-                [usize::from(start_column)..usize::from(end_column)]
-            {
-                *top_color = color;
-                write_cell(&mut self.stdout, *top_color, *bottom_color)?;
+            fn next(&mut self) -> Option<Color> {
+                self.produced += 1;
+                self.index += 1;
+                Some(Color::AnsiValue(self.index - 1))
+            }
+
+            fn nth(&mut self, n: usize) -> Option<Color> {
+                self.index += n as u8;
+                self.next()
             }
         }
 
-        Ok(())
-    }
+        let (mut display, _) = test_util::display(TerminalDisplay::builder(), 4, 2);
+        let area = Rectangle::new(Point::new(-2, -3), Size::new(8, 10));
+        let mut colors = Indices {
+            index: 0,
+            produced: 0,
+        };
+        display.fill_contiguous(&area, &mut colors).unwrap();
 
-    fn clear(&mut self, color: Color) -> io::Result<()> {
-        let (width, height) = self.resize()?;
-        self.fill_solid_aligned(0..width, 0..height, color)
+        // Only the 4x4 pixels which are actually visible get produced.
+        assert_eq!(colors.produced, 16);
+        for point in display.bounding_box().points() {
+            let index = (point.y + 3) * 8 + point.x + 2;
+            assert_eq!(
+                display.get_pixel(point),
+                Some(Color::AnsiValue(index as u8)),
+                "{point:?}"
+            );
+        }
+
+        // The same goes for pixels outside the clip area.
+        display.push_clip(Rectangle::new(Point::new(1, 1), Size::new(2, 2)));
+        let mut colors = Indices {
+            index: 0,
+            produced: 0,
+        };
+        display.fill_contiguous(&area, &mut colors).unwrap();
+        assert_eq!(colors.produced, 4);
     }
 }
@@ -0,0 +1,80 @@
+use std::io::{BufWriter, Stdout, StdoutLock};
+use std::ops::{Deref, DerefMut};
+
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+use crate::{Color, TerminalDisplay, TerminalError};
+
+/// A [`TerminalDisplay`] writing to stdout, along with a lock on stdout,
+/// created by [`TerminalDisplay::lock`].
+///
+/// While this exists, other threads which try to write to stdout (including
+/// with `println!`) wait until it's dropped, so they can't end up writing in
+/// the middle of the display's output. The display itself can still write to
+/// stdout, since that's done from the same thread.
+///
+/// This can be used as the display itself, and drawn to directly.
+///
+/// [`TerminalDisplay`]: crate::TerminalDisplay
+/// [`TerminalDisplay::lock`]: crate::TerminalDisplay::lock
+pub struct DisplayLock<'a> {
+    display: &'a mut TerminalDisplay<BufWriter<Stdout>>,
+    _lock: StdoutLock<'static>,
+}
+
+impl<'a> DisplayLock<'a> {
+    pub(crate) fn new(display: &'a mut TerminalDisplay<BufWriter<Stdout>>) -> Self {
+        Self {
+            display,
+            _lock: std::io::stdout().lock(),
+        }
+    }
+}
+
+impl Deref for DisplayLock<'_> {
+    type Target = TerminalDisplay<BufWriter<Stdout>>;
+
+    fn deref(&self) -> &Self::Target {
+        self.display
+    }
+}
+
+impl DerefMut for DisplayLock<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.display
+    }
+}
+
+impl OriginDimensions for DisplayLock<'_> {
+    fn size(&self) -> Size {
+        self.display.size()
+    }
+}
+
+impl DrawTarget for DisplayLock<'_> {
+    type Color = Color;
+    type Error = TerminalError;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), TerminalError>
+    where
+        I: IntoIterator<Item = Pixel<Color>>,
+    {
+        self.display.draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), TerminalError>
+    where
+        I: IntoIterator<Item = Color>,
+    {
+        self.display.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Color) -> Result<(), TerminalError> {
+        self.display.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), TerminalError> {
+        self.display.clear(color)
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+/// How many frames [`TerminalDisplay::present_at_most`] has drawn, and how
+/// many it's skipped because the output couldn't keep up.
+///
+/// Frames only get skipped once
+/// [`TerminalDisplay::set_adaptive_frame_rate`] has been turned on.
+///
+/// [`TerminalDisplay::present_at_most`]: crate::TerminalDisplay::present_at_most
+/// [`TerminalDisplay::set_adaptive_frame_rate`]: crate::TerminalDisplay::set_adaptive_frame_rate
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FrameStats {
+    /// The number of frames which were presented.
+    pub drawn: u64,
+    /// The number of frames which were skipped, leaving what was drawn to be
+    /// presented with the next frame.
+    pub skipped: u64,
+}
+
+/// An estimate of how fast the terminal takes what's written to it, for
+/// working out whether there's time to write the next frame.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OutputRate {
+    /// The estimated number of bytes per second that can be written, or `None`
+    /// if nothing's been measured yet.
+    bytes_per_second: Option<f64>,
+    /// When the last frame that was actually presented started being written.
+    last_drawn: Option<Instant>,
+}
+
+impl OutputRate {
+    /// How much each new measurement counts towards the estimate, as opposed to
+    /// the ones before it.
+    const WEIGHT: f64 = 0.25;
+
+    /// Update the estimate after writing `bytes` bytes, starting at `start`
+    /// and finishing now.
+    pub(crate) fn record(&mut self, bytes: usize, start: Instant) {
+        // Writes which don't block at all would otherwise look infinitely fast.
+        let elapsed = start.elapsed().max(Duration::from_millis(1)).as_secs_f64();
+        let measured = bytes as f64 / elapsed;
+        self.bytes_per_second = Some(match self.bytes_per_second {
+            Some(estimate) => estimate + (measured - estimate) * Self::WEIGHT,
+            None => measured,
+        });
+        self.last_drawn = Some(start);
+    }
+
+    /// Whether writing `bytes` more bytes now would keep up with the output.
+    ///
+    /// That's the case if it's been at least as long since the last frame
+    /// started being written as writing these bytes is expected to take. If
+    /// writing blocks until the output's taken everything, that's always true
+    /// by the time the next frame comes along; but if the output buffers what's
+    /// written, this keeps it from piling up more than it can get through.
+    pub(crate) fn keeps_up(&self, bytes: usize) -> bool {
+        match (self.bytes_per_second, self.last_drawn) {
+            (Some(bytes_per_second), Some(last_drawn)) => {
+                last_drawn.elapsed().as_secs_f64() >= bytes as f64 / bytes_per_second
+            }
+            _ => true,
+        }
+    }
+}
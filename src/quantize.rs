@@ -0,0 +1,416 @@
+use std::env;
+use std::sync::OnceLock;
+
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::prelude::*;
+
+use crate::color::LinearRgb;
+use crate::Color;
+
+/// The range of colors which the terminal can display.
+///
+/// Colors which the terminal can't display get replaced by the closest color
+/// that it can.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorMode {
+    /// Any RGB color can be displayed.
+    #[default]
+    TrueColor,
+
+    /// Only the 256 colors of the xterm palette can be displayed.
+    ///
+    /// [`Color::Rgb`] is replaced by the closest [`Color::AnsiValue`].
+    Ansi256,
+
+    /// Only the 16 named colors can be displayed.
+    ///
+    /// [`Color::Rgb`] and [`Color::AnsiValue`] are replaced by the closest
+    /// named color.
+    Ansi16,
+
+    /// Only the 8 basic colors can be displayed, without the bright variants
+    /// the 16 named colors add: that's [`Color::Black`], [`Color::Grey`] and
+    /// the dark named colors.
+    ///
+    /// The bright named colors are replaced by their basic versions (e.g.
+    /// [`Color::Red`] by [`Color::DarkRed`], and [`Color::DarkGrey`] by
+    /// [`Color::Black`]), and every other color by the closest basic color.
+    Ansi8,
+
+    /// Only the colors in the given palette are used, for giving the display
+    /// the look of a particular retro console or computer. A couple of those
+    /// palettes are built in, like [`ColorMode::PICO_8`].
+    ///
+    /// Every color is replaced by the closest color in the palette (measured
+    /// in linear RGB), apart from the terminal's default colors, which are
+    /// left alone unless the palette has RGB colors for them (see
+    /// [`TerminalDisplayBuilder::canvas_color`]). The palette can contain any
+    /// kind of color, but only the ones with known RGB values are ever picked.
+    /// An empty palette leaves every color alone.
+    ///
+    /// [`TerminalDisplayBuilder::canvas_color`]: crate::TerminalDisplayBuilder::canvas_color
+    Palette(Vec<Color>),
+
+    /// Work out which colors the terminal can display from the environment,
+    /// and use the best of the other modes it supports.
+    ///
+    /// This is worked out once when the display is created, and can be
+    /// checked with [`TerminalDisplay::detected_color_mode`]. The `COLORTERM`
+    /// environment variable being `truecolor` or `24bit` means
+    /// [`ColorMode::TrueColor`], `TERM` mentioning `256color` means
+    /// [`ColorMode::Ansi256`], the Linux console means [`ColorMode::Ansi8`],
+    /// and anything else falls back to [`ColorMode::Ansi16`]. Terminals don't
+    /// always set these correctly, so if this picks the wrong mode, pick one
+    /// explicitly instead.
+    ///
+    /// [`TerminalDisplay::detected_color_mode`]: crate::TerminalDisplay::detected_color_mode
+    Auto,
+}
+
+impl ColorMode {
+    /// The 16-color palette of the PICO-8 fantasy console.
+    pub const PICO_8: [Color; 16] = [
+        Color::Rgb(Rgb888::new(0x00, 0x00, 0x00)),
+        Color::Rgb(Rgb888::new(0x1d, 0x2b, 0x53)),
+        Color::Rgb(Rgb888::new(0x7e, 0x25, 0x53)),
+        Color::Rgb(Rgb888::new(0x00, 0x87, 0x51)),
+        Color::Rgb(Rgb888::new(0xab, 0x52, 0x36)),
+        Color::Rgb(Rgb888::new(0x5f, 0x57, 0x4f)),
+        Color::Rgb(Rgb888::new(0xc2, 0xc3, 0xc7)),
+        Color::Rgb(Rgb888::new(0xff, 0xf1, 0xe8)),
+        Color::Rgb(Rgb888::new(0xff, 0x00, 0x4d)),
+        Color::Rgb(Rgb888::new(0xff, 0xa3, 0x00)),
+        Color::Rgb(Rgb888::new(0xff, 0xec, 0x27)),
+        Color::Rgb(Rgb888::new(0x00, 0xe4, 0x36)),
+        Color::Rgb(Rgb888::new(0x29, 0xad, 0xff)),
+        Color::Rgb(Rgb888::new(0x83, 0x76, 0x9c)),
+        Color::Rgb(Rgb888::new(0xff, 0x77, 0xa8)),
+        Color::Rgb(Rgb888::new(0xff, 0xcc, 0xaa)),
+    ];
+
+    /// The four shades of green of the original Game Boy's screen, from
+    /// darkest to lightest.
+    pub const GAME_BOY: [Color; 4] = [
+        Color::Rgb(Rgb888::new(0x0f, 0x38, 0x0f)),
+        Color::Rgb(Rgb888::new(0x30, 0x62, 0x30)),
+        Color::Rgb(Rgb888::new(0x8b, 0xac, 0x0f)),
+        Color::Rgb(Rgb888::new(0x9b, 0xbc, 0x0f)),
+    ];
+
+    /// Get the mode to actually use for this mode, which is the detected mode
+    /// for [`ColorMode::Auto`].
+    pub(crate) fn resolve(self) -> ColorMode {
+        static DETECTED: OnceLock<ColorMode> = OnceLock::new();
+        match self {
+            ColorMode::Auto => DETECTED
+                .get_or_init(|| {
+                    ColorMode::detect(
+                        env::var("COLORTERM").ok().as_deref(),
+                        env::var("TERM").ok().as_deref(),
+                    )
+                })
+                .clone(),
+            mode => mode,
+        }
+    }
+
+    /// Work out which colors the terminal can display, from the values of the
+    /// `COLORTERM` and `TERM` environment variables.
+    fn detect(colorterm: Option<&str>, term: Option<&str>) -> ColorMode {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return ColorMode::TrueColor;
+        }
+        match term {
+            Some(term) if term.contains("truecolor") || term.contains("24bit") => {
+                ColorMode::TrueColor
+            }
+            Some(term) if term.contains("256color") => ColorMode::Ansi256,
+            Some("linux") => ColorMode::Ansi8,
+            _ => ColorMode::Ansi16,
+        }
+    }
+
+    /// Replace `color` with the closest color the terminal can display in this
+    /// mode.
+    pub(crate) fn quantize(&self, color: Color) -> Color {
+        match (self, color) {
+            (ColorMode::Palette(palette), color) => match color.to_rgb888() {
+                Some(rgb) if !palette.contains(&color) => {
+                    nearest_in_palette(palette, rgb).unwrap_or(color)
+                }
+                _ => color,
+            },
+            (ColorMode::TrueColor, color) => color,
+            (ColorMode::Ansi256, Color::Rgb(rgb)) => Color::AnsiValue(nearest_ansi_256(rgb)),
+            (ColorMode::Ansi16, Color::Rgb(rgb)) => nearest_named(&ANSI_16, rgb),
+            (ColorMode::Ansi16, Color::AnsiValue(n)) => match ANSI_16.get(usize::from(n)) {
+                Some(&(color, _)) => color,
+                None => nearest_named(&ANSI_16, ansi_256_rgb(n)),
+            },
+            (ColorMode::Ansi8, color) => {
+                let index = match color {
+                    Color::AnsiValue(n) if n < 16 => Some(usize::from(n)),
+                    color => ANSI_16.iter().position(|&(named, _)| named == color),
+                };
+                match (index, color.to_rgb888()) {
+                    // The bright colors are numbered 8 after their basic versions, which is
+                    // what terminals fall back to for them anyway.
+                    (Some(index), _) => ANSI_8[index % 8].0,
+                    (None, Some(rgb)) => nearest_named(ANSI_8, rgb),
+                    // The terminal's default colors don't need to be replaced.
+                    (None, None) => color,
+                }
+            }
+            (_, color) => color,
+        }
+    }
+}
+
+/// The 16 named colors, in order of their ANSI color codes, along with their
+/// RGB values in xterm's default palette.
+pub(crate) const ANSI_16: [(Color, Rgb888); 16] = [
+    (Color::Black, Rgb888::new(0, 0, 0)),
+    (Color::DarkRed, Rgb888::new(205, 0, 0)),
+    (Color::DarkGreen, Rgb888::new(0, 205, 0)),
+    (Color::DarkYellow, Rgb888::new(205, 205, 0)),
+    (Color::DarkBlue, Rgb888::new(0, 0, 238)),
+    (Color::DarkMagenta, Rgb888::new(205, 0, 205)),
+    (Color::DarkCyan, Rgb888::new(0, 205, 205)),
+    (Color::Grey, Rgb888::new(229, 229, 229)),
+    (Color::DarkGrey, Rgb888::new(127, 127, 127)),
+    (Color::Red, Rgb888::new(255, 0, 0)),
+    (Color::Green, Rgb888::new(0, 255, 0)),
+    (Color::Yellow, Rgb888::new(255, 255, 0)),
+    (Color::Blue, Rgb888::new(92, 92, 255)),
+    (Color::Magenta, Rgb888::new(255, 0, 255)),
+    (Color::Cyan, Rgb888::new(0, 255, 255)),
+    (Color::White, Rgb888::new(255, 255, 255)),
+];
+
+/// The 8 basic colors, which are the first 8 of the named colors.
+const ANSI_8: &[(Color, Rgb888)] = ANSI_16.split_at(8).0;
+
+/// The levels each channel can take in the 6x6x6 color cube of the 256-color
+/// palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Get the RGB value of a color in xterm's default 256-color palette.
+pub(crate) fn ansi_256_rgb(index: u8) -> Rgb888 {
+    match index {
+        0..=15 => ANSI_16[usize::from(index)].1,
+        16..=231 => {
+            let index = index - 16;
+            Rgb888::new(
+                CUBE_LEVELS[usize::from(index / 36)],
+                CUBE_LEVELS[usize::from(index / 6 % 6)],
+                CUBE_LEVELS[usize::from(index % 6)],
+            )
+        }
+        232..=255 => {
+            let level = 8 + 10 * (index - 232);
+            Rgb888::new(level, level, level)
+        }
+    }
+}
+
+/// Get the squared Euclidean distance between two colors.
+fn distance(a: Rgb888, b: Rgb888) -> u32 {
+    let channel = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+    channel(a.r(), b.r()) + channel(a.g(), b.g()) + channel(a.b(), b.b())
+}
+
+/// Find the color in `palette` closest to `rgb`.
+fn nearest_named(palette: &[(Color, Rgb888)], rgb: Rgb888) -> Color {
+    palette
+        .iter()
+        .min_by_key(|&&(_, candidate)| distance(rgb, candidate))
+        .map(|&(color, _)| color)
+        .unwrap()
+}
+
+/// Find the color with a known RGB value in `palette` closest to `rgb`,
+/// measuring the distance in linear RGB.
+///
+/// Returns `None` if none of the colors have known RGB values.
+fn nearest_in_palette(palette: &[Color], rgb: Rgb888) -> Option<Color> {
+    let target = LinearRgb::from_srgb(rgb);
+    palette
+        .iter()
+        .filter_map(|&color| {
+            let candidate = LinearRgb::from_srgb(color.to_rgb888()?);
+            Some((color, target.distance_squared(candidate)))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(color, _)| color)
+}
+
+/// Find the index of the color in the 256-color palette closest to `rgb`.
+fn nearest_ansi_256(rgb: Rgb888) -> u8 {
+    // Rather than checking all 256 colors, only check the closest one in each part
+    // of the palette.
+    let nearest_level = |channel: u8| {
+        (0..6)
+            .min_by_key(|&i| CUBE_LEVELS[usize::from(i)].abs_diff(channel))
+            .unwrap()
+    };
+    let cube =
+        16 + 36 * nearest_level(rgb.r()) + 6 * nearest_level(rgb.g()) + nearest_level(rgb.b());
+
+    let average = (u16::from(rgb.r()) + u16::from(rgb.g()) + u16::from(rgb.b())) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    (0..16)
+        .chain([cube, gray])
+        .min_by_key(|&index| distance(rgb, ansi_256_rgb(index)))
+        .unwrap()
+}
+
+/// How to reduce banding when colors are replaced by the closest color the
+/// terminal can display.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Dithering {
+    /// Every color is replaced by the closest available color, on its own.
+    #[default]
+    None,
+
+    /// The error from replacing each color is spread out to the pixels around
+    /// it using Floyd–Steinberg dithering.
+    ///
+    /// This only applies to [`DrawTarget::fill_contiguous`], since other ways
+    /// of drawing don't have neighbouring pixels to spread the error to.
+    ///
+    /// [`DrawTarget::fill_contiguous`]: embedded_graphics_core::draw_target::DrawTarget::fill_contiguous
+    FloydSteinberg,
+}
+
+/// The state of Floyd–Steinberg dithering across a rectangle of pixels, which
+/// are quantized one at a time in row-major order.
+#[derive(Default)]
+pub(crate) struct FloydSteinberg {
+    color_mode: ColorMode,
+    /// If this is set, colors are turned into black or white depending on
+    /// whether their luminance is above it, rather than quantized using
+    /// `color_mode`.
+    threshold: Option<f32>,
+    /// The error which has been spread to each pixel in the current row.
+    ///
+    /// These have an extra pixel of padding on each side, so that we don't need
+    /// to worry about spreading error off the edges.
+    current: Vec<[i32; 3]>,
+    /// The error which has been spread to each pixel in the next row.
+    next: Vec<[i32; 3]>,
+}
+
+impl FloydSteinberg {
+    /// Start dithering a new rectangle of pixels which is `width` pixels wide,
+    /// reusing the memory from the last one.
+    ///
+    /// If `threshold` is set, colors are turned into black or white depending
+    /// on whether their luminance is above it, rather than quantized using
+    /// `color_mode`.
+    pub(crate) fn reset(&mut self, color_mode: &ColorMode, threshold: Option<f32>, width: usize) {
+        self.color_mode.clone_from(color_mode);
+        self.threshold = threshold;
+        for errors in [&mut self.current, &mut self.next] {
+            errors.clear();
+            errors.resize(width + 2, [0; 3]);
+        }
+    }
+
+    /// Quantize the color of the pixel at `x` in the current row.
+    pub(crate) fn quantize(&mut self, x: usize, color: Color) -> Color {
+        let rgb = match color {
+            Color::Rgb(rgb) => rgb,
+            // Only RGB colors get dithered; everything else is just quantized, or left for
+            // the renderer to threshold.
+            _ if self.threshold.is_some() => return color,
+            _ => return self.color_mode.quantize(color),
+        };
+
+        // Account for the padding.
+        let x = x + 1;
+        let channels = [rgb.r(), rgb.g(), rgb.b()];
+        let wanted: [i32; 3] =
+            std::array::from_fn(|i| (i32::from(channels[i]) + self.current[x][i]).clamp(0, 255));
+        let wanted_color = Color::Rgb(Rgb888::new(
+            wanted[0] as u8,
+            wanted[1] as u8,
+            wanted[2] as u8,
+        ));
+        let quantized = match self.threshold {
+            Some(threshold) if wanted_color.luminance() > threshold => Color::Rgb(Rgb888::WHITE),
+            Some(_) => Color::Rgb(Rgb888::BLACK),
+            None => self.color_mode.quantize(wanted_color),
+        };
+
+        // Quantizing an RGB color always gives a color which has an RGB value.
+        let actual = quantized.to_rgb888().unwrap();
+        let actual = [actual.r(), actual.g(), actual.b()];
+        let error: [i32; 3] = std::array::from_fn(|i| wanted[i] - i32::from(actual[i]));
+
+        for (channel, error) in error.into_iter().enumerate() {
+            self.current[x + 1][channel] += error * 7 / 16;
+            self.next[x - 1][channel] += error * 3 / 16;
+            self.next[x][channel] += error * 5 / 16;
+            self.next[x + 1][channel] += error / 16;
+        }
+
+        quantized
+    }
+
+    /// Move on to the next row.
+    pub(crate) fn next_row(&mut self) {
+        std::mem::swap(&mut self.current, &mut self.next);
+        self.next.fill([0; 3]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics_core::pixelcolor::Rgb888;
+
+    use super::{ColorMode, FloydSteinberg, ANSI_8};
+    use crate::Color;
+
+    #[test]
+    fn dithering_diffuses_error() {
+        let gradient = |x: usize| Color::Rgb(Rgb888::new(x as u8 * 16, 0, 0));
+        let plain: Vec<_> = (0..16)
+            .map(|x| ColorMode::Ansi16.quantize(gradient(x)))
+            .collect();
+
+        let mut dither = FloydSteinberg::default();
+        dither.reset(&ColorMode::Ansi16, None, 16);
+        let mut dithered = Vec::new();
+        for _ in 0..2 {
+            dithered.extend((0..16).map(|x| dither.quantize(x, gradient(x))));
+            dither.next_row();
+        }
+
+        let changed = plain
+            .iter()
+            .cycle()
+            .zip(&dithered)
+            .filter(|(plain, dithered)| plain != dithered)
+            .count();
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn ansi_8_greys() {
+        let is_basic = |color| ANSI_8.iter().any(|&(basic, _)| basic == color);
+        // `DarkGrey` is bright black, and `Grey` is the basic white.
+        assert_eq!(ColorMode::Ansi8.quantize(Color::DarkGrey), Color::Black);
+        assert_eq!(ColorMode::Ansi8.quantize(Color::Grey), Color::Grey);
+        for color in [
+            Color::DarkGrey,
+            Color::Grey,
+            Color::AnsiValue(8),
+            Color::AnsiValue(244),
+            Color::Rgb(Rgb888::new(128, 128, 128)),
+            Color::Rgb(Rgb888::new(64, 64, 64)),
+        ] {
+            assert!(is_basic(ColorMode::Ansi8.quantize(color)), "{color:?}");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use crossterm::terminal;
+
+/// A writer which passes everything through to another writer, while also
+/// recording it to an [asciicast v2] file so that it can be played back later
+/// (e.g. with `asciinema play`).
+///
+/// Everything written between flushes becomes a single event in the
+/// recording, timestamped with when it was flushed.
+///
+/// [asciicast v2]: https://docs.asciinema.org/manual/asciicast/v2/
+pub struct Recorder<W> {
+    inner: W,
+    cast: BufWriter<File>,
+    /// When the recording started, which timestamps are relative to.
+    start: Instant,
+    /// Everything that's been written since the last flush.
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Create a recorder which writes to `inner`, and records to a new file at
+    /// `path`.
+    ///
+    /// The size recorded for the terminal is its size right now.
+    pub fn new(inner: W, path: impl AsRef<Path>) -> io::Result<Self> {
+        let (width, height) = terminal::size()?;
+        let mut cast = BufWriter::new(File::create(path)?);
+        writeln!(
+            cast,
+            r#"{{"version": 2, "width": {width}, "height": {height}}}"#
+        )?;
+        Ok(Self {
+            inner,
+            cast,
+            start: Instant::now(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Get the writer that everything's being passed through to.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+}
+
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.pending.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // A flush can fall in the middle of a character, in which case the rest of it
+        // is left for the next one.
+        let len = complete_len(&self.pending);
+        if len > 0 {
+            let elapsed = self.start.elapsed().as_secs_f64();
+            let data = json_string(&String::from_utf8_lossy(&self.pending[..len]));
+            writeln!(self.cast, "[{elapsed:.6}, \"o\", {data}]")?;
+            self.cast.flush()?;
+            self.pending.drain(..len);
+        }
+        self.inner.flush()
+    }
+}
+
+/// Get the length of `bytes` without the UTF-8 sequence at the end, if it's
+/// been cut off partway through.
+fn complete_len(bytes: &[u8]) -> usize {
+    // A character is at most 4 bytes long, so only the last 3 bytes can start one
+    // which is cut off.
+    for (i, &byte) in bytes.iter().enumerate().rev().take(3) {
+        let len = match byte {
+            // This is a continuation byte, so keep looking for the start.
+            0x80..=0xbf => continue,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => break,
+        };
+        if bytes.len() - i < len {
+            return i;
+        }
+        break;
+    }
+    bytes.len()
+}
+
+/// Encode `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            // Escape sequences start with ESC, so this comes up a lot.
+            c if c.is_control() => {
+                // Writing to a `String` can't fail.
+                write!(out, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::{BufWriter, Write};
+    use std::time::Instant;
+
+    use super::Recorder;
+
+    #[test]
+    fn flush_mid_character() {
+        let path = std::env::temp_dir().join(format!("recorder-test-{}.cast", std::process::id()));
+        let mut recorder = Recorder {
+            inner: Vec::new(),
+            cast: BufWriter::new(File::create(&path).unwrap()),
+            start: Instant::now(),
+            pending: Vec::new(),
+        };
+        let glyph = "▀".as_bytes();
+        recorder.write_all(b"a").unwrap();
+        recorder.write_all(&glyph[..1]).unwrap();
+        recorder.flush().unwrap();
+        recorder.write_all(&glyph[1..]).unwrap();
+        recorder.flush().unwrap();
+        // There's nothing left over, so this doesn't record anything.
+        recorder.flush().unwrap();
+        drop(recorder);
+
+        let cast = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let events: Vec<_> = cast
+            .lines()
+            .map(|line| line.split_once(", ").unwrap().1)
+            .collect();
+        assert_eq!(events, ["\"o\", \"a\"]", "\"o\", \"▀\"]"]);
+    }
+}
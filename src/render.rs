@@ -0,0 +1,641 @@
+#[cfg(feature = "sixel")]
+use std::env;
+
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::prelude::*;
+
+use crate::{Color, ColorMode};
+
+/// The way pixels are mapped onto the character cells of the terminal.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RenderMode {
+    /// Each cell is split into a top and bottom pixel, drawn using the half
+    /// block characters (`▀`, `▄` and `█`).
+    ///
+    /// Both pixels can be any color.
+    #[default]
+    HalfBlock,
+
+    /// Each cell is split into 2x4 pixels, drawn using Braille patterns
+    /// (U+2800 to U+28FF).
+    ///
+    /// This is monochrome: a pixel is off if it's [`Color::BgColor`], and on
+    /// if it's any other color. All the pixels in a cell which are on are
+    /// drawn in the same color, which is whichever color most of them are.
+    ///
+    /// For photos and other images without a background, pixels can be turned
+    /// on or off based on their brightness instead, using
+    /// [`TerminalDisplay::set_braille_threshold`].
+    ///
+    /// [`TerminalDisplay::set_braille_threshold`]: crate::TerminalDisplay::set_braille_threshold
+    Braille,
+
+    /// Each cell is split into 2x2 pixels, drawn using the quadrant block
+    /// characters (U+2596 to U+259F, along with `▀`, `▄`, `▌`, `▐` and `█`).
+    ///
+    /// A cell can only be drawn in two colors, so if its pixels have more than
+    /// two different colors, only the two most common colors are kept, and
+    /// the rest of the pixels are drawn in the most common color.
+    Quadrant,
+
+    /// Each cell is split into 2x3 pixels, drawn using the sextant characters
+    /// from the Symbols for Legacy Computing block (U+1FB00 to U+1FB3B, along
+    /// with `▌`, `▐` and `█`).
+    ///
+    /// Like [`RenderMode::Quadrant`], a cell can only be drawn in two colors.
+    ///
+    /// Not all fonts include these characters; if they don't show up properly,
+    /// [`TerminalDisplay::set_sextant_fallback`] can be used to draw them with
+    /// quadrant characters instead.
+    ///
+    /// [`TerminalDisplay::set_sextant_fallback`]: crate::TerminalDisplay::set_sextant_fallback
+    Sextant,
+
+    /// Each cell is a single pixel, drawn as a space with the pixel's color as
+    /// its background; or a full block (`█`) for [`Color::FgColor`], which
+    /// can only be shown as the foreground.
+    ///
+    /// This gives the display the same size as the terminal, which suits
+    /// coarse, low-resolution output like dashboards.
+    FullCell,
+
+    /// Each cell is split into 1x8 pixels, drawn as a bar going up from the
+    /// bottom of the cell using the lower block characters (U+2581 to U+2588).
+    ///
+    /// This is meant for bar charts and audio visualizers, where each column
+    /// of pixels is filled in up to some height. Like [`RenderMode::Braille`],
+    /// it's monochrome: the bar is as tall as the number of pixels in the cell
+    /// which aren't [`Color::BgColor`], wherever they are in it, and it's
+    /// drawn in whichever color most of them are, over the terminal's
+    /// background.
+    VerticalBars,
+
+    /// Each cell is split into 8x1 pixels, drawn as a bar going right from the
+    /// left edge of the cell using the left block characters (U+2588 to
+    /// U+258F).
+    ///
+    /// This is the sideways version of [`RenderMode::VerticalBars`], for
+    /// progress bars and sparklines: the bar is as long as the number of
+    /// pixels in the cell which aren't [`Color::BgColor`].
+    HorizontalBars,
+}
+
+impl RenderMode {
+    /// Get the size of a single cell in pixels.
+    pub(crate) fn cell_size(self) -> Size {
+        match self {
+            RenderMode::HalfBlock => Size::new(1, 2),
+            RenderMode::Braille => Size::new(2, 4),
+            RenderMode::Quadrant => Size::new(2, 2),
+            RenderMode::Sextant => Size::new(2, 3),
+            RenderMode::FullCell => Size::new(1, 1),
+            RenderMode::VerticalBars => Size::new(1, 8),
+            RenderMode::HorizontalBars => Size::new(8, 1),
+        }
+    }
+
+    /// Get the number of pixels in a single cell.
+    pub(crate) fn cell_pixel_count(self) -> usize {
+        let size = self.cell_size();
+        size.width as usize * size.height as usize
+    }
+}
+
+/// The way the display gets drawn to the terminal.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Each cell of the terminal is drawn as a character, which is split up
+    /// into pixels according to the [`RenderMode`].
+    ///
+    /// This works on any terminal.
+    #[default]
+    Cells,
+
+    /// The display is drawn as a sixel image, with one of the display's pixels
+    /// for each of the terminal's pixels.
+    ///
+    /// `cell_size` is the size of one of the terminal's cells in pixels, which
+    /// it can't report; each side of it is clamped to between 1 and 255. The
+    /// size of the display is the size of the terminal in cells times that,
+    /// except that the bottom row of cells is left out, since drawing a sixel
+    /// image there makes most terminals scroll.
+    ///
+    /// Only the rows of cells which have changed get redrawn on each flush.
+    /// The render mode and glyph set aren't used, and neither are the
+    /// attributes set with [`TerminalDisplay::set_attr`], apart from on text
+    /// written with [`TerminalDisplay::put_str`], which is drawn over the top
+    /// of the image. Sixel images can't use the terminal's default colors, so
+    /// they're drawn with the RGB values set with
+    /// [`TerminalDisplay::set_default_colors`] instead.
+    ///
+    /// [`Backend::sixel_if_supported`] only picks this for terminals which
+    /// look like they support sixels.
+    ///
+    /// [`TerminalDisplay::set_attr`]: crate::TerminalDisplay::set_attr
+    /// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+    /// [`TerminalDisplay::set_default_colors`]: crate::TerminalDisplay::set_default_colors
+    /// [`Backend::sixel_if_supported`]: crate::Backend::sixel_if_supported
+    #[cfg(feature = "sixel")]
+    Sixel {
+        /// The size of a cell in pixels.
+        cell_size: Size,
+    },
+}
+
+impl Backend {
+    /// Get [`Backend::Sixel`] with the given cell size if the terminal looks
+    /// like it supports sixels, or [`Backend::Cells`] if it doesn't.
+    ///
+    /// This is a guess from the `TERM` and `TERM_PROGRAM` environment
+    /// variables, since actually asking the terminal means reading its reply
+    /// from stdin, so it can be wrong either way.
+    ///
+    /// [`Backend::Sixel`]: crate::Backend::Sixel
+    /// [`Backend::Cells`]: crate::Backend::Cells
+    #[cfg(feature = "sixel")]
+    pub fn sixel_if_supported(cell_size: Size) -> Backend {
+        if crate::sixel::supported(
+            env::var("TERM").ok().as_deref(),
+            env::var("TERM_PROGRAM").ok().as_deref(),
+        ) {
+            Backend::Sixel { cell_size }
+        } else {
+            Backend::Cells
+        }
+    }
+}
+
+/// How the display is flipped or rotated when it's drawn to the terminal.
+///
+/// This only changes where things show up on the terminal: drawing
+/// coordinates, [`OriginDimensions::size`] and everything else about the
+/// display stay the same, so whatever's drawing to it doesn't need to know.
+/// Text written with [`TerminalDisplay::put_str`] is written the right way
+/// round, in the cells it's given.
+///
+/// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    /// The display is drawn as it is.
+    #[default]
+    Normal,
+
+    /// The display is mirrored horizontally, so that its left edge is on the
+    /// right.
+    FlipH,
+
+    /// The display is mirrored vertically, so that its top edge is at the
+    /// bottom.
+    FlipV,
+
+    /// The display is rotated by 180°, which is the same as flipping it both
+    /// horizontally and vertically.
+    Rotate180,
+}
+
+impl Orientation {
+    /// Whether this mirrors the display horizontally.
+    pub(crate) fn flip_x(self) -> bool {
+        matches!(self, Orientation::FlipH | Orientation::Rotate180)
+    }
+
+    /// Whether this mirrors the display vertically.
+    pub(crate) fn flip_y(self) -> bool {
+        matches!(self, Orientation::FlipV | Orientation::Rotate180)
+    }
+
+    /// Get the pixel of a display of the given size which shows up where
+    /// `point` would normally be, or vice versa.
+    ///
+    /// `point` has to be inside the display.
+    pub(crate) fn apply(self, size: Size, point: Point) -> Point {
+        // The point is inside the display, so these can't overflow.
+        Point::new(
+            if self.flip_x() {
+                size.width as i32 - 1 - point.x
+            } else {
+                point.x
+            },
+            if self.flip_y() {
+                size.height as i32 - 1 - point.y
+            } else {
+                point.y
+            },
+        )
+    }
+}
+
+/// The set of characters used to draw cells.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GlyphSet {
+    /// The Unicode block characters appropriate to the render mode.
+    #[default]
+    Unicode,
+
+    /// Plain ASCII characters, for terminals or fonts which can't display the
+    /// Unicode block characters.
+    ///
+    /// This is only an approximation, and only uses `#`, `"`, `,` and spaces:
+    ///
+    /// - In [`RenderMode::HalfBlock`], [`RenderMode::Braille`],
+    ///   [`RenderMode::Quadrant`], [`RenderMode::Sextant`] and
+    ///   [`RenderMode::FullCell`], cells are drawn as `#`, `"`, `,` or a space
+    ///   depending on whether their top and bottom halves have anything filled
+    ///   in. The middle row of a sextant cell counts as part of both halves.
+    /// - In [`RenderMode::VerticalBars`], a bar is drawn as `,` if it's at most
+    ///   half the cell's height and `#` if it's any taller; only its height
+    ///   matters.
+    /// - In [`RenderMode::HorizontalBars`], a bar is drawn as `#` once it
+    ///   covers at least half of the cell's width, and as a space otherwise.
+    Ascii,
+}
+
+/// An adjustment to the brightness and gamma of RGB colors, which is applied
+/// to each of their channels as they're displayed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Levels {
+    pub(crate) brightness: f32,
+    pub(crate) gamma: f32,
+    /// What each value of a channel becomes, or `None` if they're all left
+    /// alone.
+    table: Option<[u8; 256]>,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels::new(1.0, 1.0)
+    }
+}
+
+impl Levels {
+    /// Make an adjustment which raises each channel (from 0 to 1) to the
+    /// power of `1 / gamma`, and then multiplies it by `brightness`.
+    pub(crate) fn new(brightness: f32, gamma: f32) -> Levels {
+        let table = (brightness != 1.0 || gamma != 1.0).then(|| {
+            std::array::from_fn(|value| {
+                let value = (value as f32 / 255.0).powf(gamma.recip()) * brightness;
+                // This saturates, so anything out of range gets clamped.
+                (value * 255.0).round() as u8
+            })
+        });
+        Levels {
+            brightness,
+            gamma,
+            table,
+        }
+    }
+
+    /// Apply the adjustment to `rgb`.
+    fn apply(&self, rgb: Rgb888) -> Rgb888 {
+        match &self.table {
+            Some(table) => Rgb888::new(
+                table[usize::from(rgb.r())],
+                table[usize::from(rgb.g())],
+                table[usize::from(rgb.b())],
+            ),
+            None => rgb,
+        }
+    }
+}
+
+/// Everything which affects how cells get drawn.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct RenderConfig {
+    pub(crate) backend: Backend,
+    pub(crate) render_mode: RenderMode,
+    pub(crate) glyph_set: GlyphSet,
+    pub(crate) color_mode: ColorMode,
+    /// Whether to draw sextants using quadrant characters, for terminals which
+    /// can't display them.
+    pub(crate) sextant_fallback: bool,
+    /// The color to draw pixels which are [`Color::BgColor`] with.
+    pub(crate) canvas_color: Color,
+    pub(crate) orientation: Orientation,
+    pub(crate) levels: Levels,
+    /// The luminance a pixel has to be above for its dot to be on in
+    /// [`RenderMode::Braille`], if dots are picked by brightness.
+    pub(crate) braille_threshold: Option<f32>,
+    /// The character and color to draw cells whose pixels are all
+    /// [`Color::BgColor`] with, rather than leaving them blank.
+    pub(crate) empty_cell: Option<(char, Color)>,
+}
+
+impl RenderConfig {
+    /// Get the size of a single cell in pixels.
+    pub(crate) fn cell_size(&self) -> Size {
+        match self.backend {
+            Backend::Cells => self.render_mode.cell_size(),
+            #[cfg(feature = "sixel")]
+            Backend::Sixel { cell_size } => Size::new(
+                cell_size.width.clamp(1, 255),
+                cell_size.height.clamp(1, 255),
+            ),
+        }
+    }
+
+    /// Whether each cell is drawn as a character, rather than the display
+    /// being drawn as an image.
+    pub(crate) fn draws_cells(&self) -> bool {
+        self.backend == Backend::Cells
+    }
+
+    /// Get the luminance a pixel has to be above to be drawn, if pixels are
+    /// currently being picked by brightness.
+    pub(crate) fn threshold(&self) -> Option<f32> {
+        match self.render_mode {
+            RenderMode::Braille => self.braille_threshold,
+            _ => None,
+        }
+    }
+
+    /// Get the color a pixel of `color` should be treated as when working out
+    /// what its cell looks like, which is [`Color::BgColor`] if it isn't
+    /// bright enough to be drawn.
+    pub(crate) fn pixel_color(&self, color: Color) -> Color {
+        match self.threshold() {
+            Some(threshold) if color.luminance() <= threshold => Color::BgColor,
+            _ => color,
+        }
+    }
+
+    /// Get the color `color` should actually be displayed as.
+    pub(crate) fn display_color(&self, color: Color) -> Color {
+        let color = match color {
+            Color::BgColor => self.canvas_color,
+            color => color,
+        };
+        let color = match color {
+            Color::Rgb(rgb) => Color::Rgb(self.levels.apply(rgb)),
+            color => color,
+        };
+        self.color_mode.quantize(color)
+    }
+
+    /// Get the RGB value `color` should actually be displayed as, given the
+    /// RGB values of the terminal's default (background, foreground) colors.
+    #[cfg(any(feature = "image", feature = "sixel"))]
+    pub(crate) fn display_rgb(
+        &self,
+        color: Color,
+        (background, foreground): (Rgb888, Rgb888),
+    ) -> Rgb888 {
+        match self.display_color(color) {
+            Color::BgColor => background,
+            Color::FgColor => foreground,
+            // Everything that gets stored in the buffer has a known RGB value apart from
+            // the default colors.
+            color => color.to_rgb888().unwrap_or(background),
+        }
+    }
+
+    /// Get the function which gives the character to draw a cell with, given a
+    /// mask of which pixels in the cell are the foreground color.
+    ///
+    /// Bit `i` of the mask corresponds to pixel `i` of the cell in row-major
+    /// order.
+    pub(crate) fn glyphs(&self) -> fn(u8) -> char {
+        match (self.glyph_set, self.render_mode) {
+            (GlyphSet::Unicode, RenderMode::HalfBlock) => half_block_char,
+            (GlyphSet::Unicode, RenderMode::Braille) => braille_char,
+            (GlyphSet::Unicode, RenderMode::Quadrant) => quadrant_char,
+            (GlyphSet::Unicode, RenderMode::Sextant) if self.sextant_fallback => {
+                sextant_fallback_char
+            }
+            (GlyphSet::Unicode, RenderMode::Sextant) => sextant_char,
+            (GlyphSet::Unicode, RenderMode::FullCell) => |mask| if mask != 0 { '█' } else { ' ' },
+            (GlyphSet::Unicode, RenderMode::VerticalBars) => vertical_bar_char,
+            (GlyphSet::Unicode, RenderMode::HorizontalBars) => horizontal_bar_char,
+            (GlyphSet::Ascii, RenderMode::HalfBlock) => |mask| ascii_char(mask & 0b1, mask & 0b10),
+            (GlyphSet::Ascii, RenderMode::Braille) => {
+                |mask| ascii_char(mask & 0b1111, mask & 0b11110000)
+            }
+            (GlyphSet::Ascii, RenderMode::Quadrant) => {
+                |mask| ascii_char(mask & 0b11, mask & 0b1100)
+            }
+            // The middle row counts as part of both halves, so that it doesn't disappear.
+            (GlyphSet::Ascii, RenderMode::Sextant) => {
+                |mask| ascii_char(mask & 0b1111, mask & 0b111100)
+            }
+            (GlyphSet::Ascii, RenderMode::FullCell) => |mask| ascii_char(mask, mask),
+            // Only the height of the bar matters, so it's always treated as being at the
+            // bottom.
+            (GlyphSet::Ascii, RenderMode::VerticalBars) => |mask| match mask.count_ones() {
+                0 => ' ',
+                1..=4 => ',',
+                _ => '#',
+            },
+            // There's no way to only fill part of a cell across, so a bar gets drawn once it
+            // covers at least half of it.
+            (GlyphSet::Ascii, RenderMode::HorizontalBars) => {
+                |mask| if mask.count_ones() >= 4 { '#' } else { ' ' }
+            }
+        }
+    }
+}
+
+/// Get the ASCII character which approximates a cell whose top and bottom
+/// halves have any of the given pixels filled in.
+fn ascii_char(top: u8, bottom: u8) -> char {
+    match (top != 0, bottom != 0) {
+        (false, false) => ' ',
+        (true, false) => '"',
+        (false, true) => ',',
+        (true, true) => '#',
+    }
+}
+
+/// Get the half block character with the given halves filled in.
+///
+/// Bit 0 of `halves` is the top half, and bit 1 is the bottom half.
+pub(crate) fn half_block_char(halves: u8) -> char {
+    const CHARS: [char; 4] = [' ', '▀', '▄', '█'];
+    CHARS[usize::from(halves)]
+}
+
+/// Get the lower block character for a bar as tall as the number of pixels
+/// set in `mask`, out of 8.
+pub(crate) fn vertical_bar_char(mask: u8) -> char {
+    match mask.count_ones() {
+        0 => ' ',
+        // U+2581 to U+2588 go from one eighth of the cell up to all of it.
+        height => char::from_u32(0x2580 + height).unwrap(),
+    }
+}
+
+/// Get the left block character for a bar as long as the number of pixels set
+/// in `mask`, out of 8.
+pub(crate) fn horizontal_bar_char(mask: u8) -> char {
+    match mask.count_ones() {
+        0 => ' ',
+        8 => '█',
+        // These go backwards, from U+2589 being seven eighths of the cell to U+258F
+        // being one eighth.
+        length => char::from_u32(0x2590 - length).unwrap(),
+    }
+}
+
+/// Get the Braille pattern character with the given dots raised.
+///
+/// The bits of `dots` correspond to the dots in row-major order, starting
+/// from the least significant bit.
+pub(crate) fn braille_char(dots: u8) -> char {
+    // Braille dots are numbered going down the left column and then the right
+    // column, except for the bottom row, which was added later and so comes last.
+    const BITS: [u8; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+    let mut pattern = 0;
+    for (i, bit) in BITS.into_iter().enumerate() {
+        if dots & (1 << i) != 0 {
+            pattern |= bit;
+        }
+    }
+    // Every value from U+2800 to U+28FF is a valid Braille pattern.
+    char::from_u32(0x2800 + u32::from(pattern)).unwrap()
+}
+
+/// Get the quadrant block character with the given quadrants filled in.
+///
+/// The bits of `quadrants` correspond to the top-left, top-right, bottom-left
+/// and bottom-right quadrants, starting from the least significant bit.
+pub(crate) fn quadrant_char(quadrants: u8) -> char {
+    const CHARS: [char; 16] = [
+        ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+    ];
+    CHARS[usize::from(quadrants)]
+}
+
+/// Get the sextant character with the given sextants filled in.
+///
+/// The bits of `sextants` correspond to the sextants in row-major order,
+/// starting from the least significant bit.
+pub(crate) fn sextant_char(sextants: u8) -> char {
+    match sextants {
+        0b000000 => ' ',
+        0b010101 => '▌',
+        0b101010 => '▐',
+        0b111111 => '█',
+        _ => {
+            // The sextant characters are in order of their masks, except that the ones
+            // which already existed elsewhere are skipped.
+            let mut offset = u32::from(sextants) - 1;
+            if sextants > 0b010101 {
+                offset -= 1;
+            }
+            if sextants > 0b101010 {
+                offset -= 1;
+            }
+            char::from_u32(0x1FB00 + offset).unwrap()
+        }
+    }
+}
+
+/// Get the quadrant character which most closely approximates the sextant
+/// character with the given sextants filled in.
+///
+/// The middle row of sextants is drawn over both the top and bottom
+/// quadrants, so that it doesn't disappear.
+pub(crate) fn sextant_fallback_char(sextants: u8) -> char {
+    let top = sextants & 0b11;
+    let middle = (sextants >> 2) & 0b11;
+    let bottom = (sextants >> 4) & 0b11;
+    quadrant_char((top | middle) | ((bottom | middle) << 2))
+}
+
+/// Reduce the pixels of a cell down to the two colors that a character can be
+/// drawn with.
+///
+/// Returns the foreground color, the background color, and a mask of which
+/// pixels should be drawn in the foreground color (where bit `i` corresponds
+/// to `pixels[i]`). The foreground or background color is `None` if no pixels
+/// use it.
+///
+/// If there are more than two colors, the two most common ones are used, and
+/// any other pixels are given the most common color.
+pub(crate) fn two_colors(pixels: &[Color]) -> (Option<Color>, Option<Color>, u8) {
+    let primary = match most_common(pixels) {
+        Some(color) => color,
+        None => return (None, None, 0),
+    };
+    let mut others = [Color::BgColor; 8];
+    let mut other_count = 0;
+    for &color in pixels {
+        if color != primary {
+            others[other_count] = color;
+            other_count += 1;
+        }
+    }
+    let secondary = most_common(&others[..other_count]);
+
+    // `FgColor` and `BgColor` can only be drawn as the foreground and background
+    // respectively, since that's where the terminal's default colors apply.
+    let (fg, bg) = match secondary {
+        None if primary == Color::FgColor => (Some(primary), None),
+        None => (None, Some(primary)),
+        Some(secondary) if primary == Color::FgColor || secondary == Color::BgColor => {
+            (Some(primary), Some(secondary))
+        }
+        Some(secondary) => (Some(secondary), Some(primary)),
+    };
+
+    let mut mask = 0;
+    for (i, &color) in pixels.iter().enumerate() {
+        // Anything which isn't one of the two colors gets the more common one, which may
+        // or may not be the foreground.
+        let is_fg = match fg {
+            Some(fg) if color == fg => true,
+            _ if Some(color) == bg => false,
+            _ => fg == Some(primary),
+        };
+        if is_fg {
+            mask |= 1 << i;
+        }
+    }
+
+    (fg, bg, mask)
+}
+
+/// Find the color which appears the most in `colors`, preferring whichever
+/// comes first in the case of a tie.
+///
+/// Returns `None` if `colors` is empty.
+pub(crate) fn most_common(colors: &[Color]) -> Option<Color> {
+    let count = |color| colors.iter().filter(|&&other| other == color).count();
+    colors
+        .iter()
+        .copied()
+        .rev()
+        .max_by_key(|&color| count(color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{horizontal_bar_char, sextant_char};
+
+    #[test]
+    fn sextant_codepoints() {
+        // Bit 0 is the top left sextant, bit 1 the top right, and so on down.
+        assert_eq!(sextant_char(0b000000), ' ');
+        assert_eq!(sextant_char(0b000001), '\u{1FB00}');
+        assert_eq!(sextant_char(0b000010), '\u{1FB01}');
+        assert_eq!(sextant_char(0b000011), '\u{1FB02}');
+        assert_eq!(sextant_char(0b010100), '\u{1FB13}');
+        // The left half already exists as U+258C, so it is skipped.
+        assert_eq!(sextant_char(0b010101), '▌');
+        assert_eq!(sextant_char(0b010110), '\u{1FB14}');
+        assert_eq!(sextant_char(0b101001), '\u{1FB27}');
+        // So is the right half, as U+2590.
+        assert_eq!(sextant_char(0b101010), '▐');
+        assert_eq!(sextant_char(0b101011), '\u{1FB28}');
+        assert_eq!(sextant_char(0b111110), '\u{1FB3B}');
+        assert_eq!(sextant_char(0b111111), '█');
+    }
+
+    #[test]
+    fn horizontal_bar_eighths() {
+        let glyphs = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+        for (length, glyph) in glyphs.into_iter().enumerate() {
+            // The bar fills in from the left.
+            let mask = (1u16 << length) - 1;
+            assert_eq!(horizontal_bar_char(mask as u8), glyph, "{length} eighths");
+        }
+    }
+}
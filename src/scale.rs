@@ -0,0 +1,196 @@
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+use crate::color::LinearRgb;
+use crate::Color;
+
+/// How to fit an image to the display when scaling it with
+/// [`TerminalDisplay::blit_scaled`].
+///
+/// Pixels are assumed to be square, which is only roughly true of most render
+/// modes.
+///
+/// [`TerminalDisplay::blit_scaled`]: crate::TerminalDisplay::blit_scaled
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Fit {
+    /// Stretch the image to cover the whole display, even if that changes its
+    /// aspect ratio.
+    Stretch,
+
+    /// Scale the image to be as big as possible while still fitting entirely
+    /// on the display, keeping its aspect ratio. It's centered on the display,
+    /// and whatever was already on the display is left alone around it.
+    #[default]
+    Contain,
+
+    /// Scale the image to be as small as possible while still covering the
+    /// whole display, keeping its aspect ratio. It's centered on the display,
+    /// and whatever hangs off the edges is cut off.
+    Cover,
+}
+
+/// How to pick the colors of pixels when scaling an image with
+/// [`TerminalDisplay::blit_scaled`].
+///
+/// [`TerminalDisplay::blit_scaled`]: crate::TerminalDisplay::blit_scaled
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Sampling {
+    /// Use the color of the image's pixel which is closest to the middle of
+    /// each of the display's pixels.
+    ///
+    /// This keeps edges sharp, which suits pixel art.
+    #[default]
+    Nearest,
+
+    /// Average the colors of all the image's pixels which each of the
+    /// display's pixels covers, in linear RGB.
+    ///
+    /// This gives smoother results when shrinking photos. The terminal's
+    /// default colors and [`Color::Transparent`] can't be averaged, so wherever
+    /// they're involved this falls back to [`Sampling::Nearest`]; the alpha of
+    /// [`Color::Rgba`] is ignored.
+    BoxAverage,
+}
+
+impl Sampling {
+    /// Sample the pixel of `source` (which is `source_size` in size) which
+    /// shows up at `point` when it's scaled to `area` in size.
+    ///
+    /// `point` is relative to the top-left of the area, and has to be inside
+    /// it.
+    pub(crate) fn sample(
+        self,
+        source: &[Color],
+        source_size: Size,
+        area: Size,
+        point: (u32, u32),
+    ) -> Color {
+        match self {
+            Sampling::Nearest => sample_nearest(source, source_size, area, point),
+            Sampling::BoxAverage => sample_box(source, source_size, area, point)
+                .unwrap_or_else(|| sample_nearest(source, source_size, area, point)),
+        }
+    }
+}
+
+impl Fit {
+    /// Get the area an image of size `source` should be scaled to, to fit it to
+    /// a display of size `display` in this way.
+    ///
+    /// The area can hang off the edges of the display with [`Fit::Cover`].
+    pub(crate) fn area(self, source: Size, display: Size) -> Rectangle {
+        // Work this out in `u64`s, so that the multiplications can't overflow.
+        let (source_width, source_height) = (u64::from(source.width), u64::from(source.height));
+        let (display_width, display_height) = (u64::from(display.width), u64::from(display.height));
+
+        // Comparing the two aspect ratios by cross-multiplying tells us whether the
+        // image's width or height is what limits how far it can be scaled up.
+        let wider = source_width * display_height > display_width * source_height;
+        let size = match (self, wider) {
+            (Fit::Stretch, _) => return Rectangle::new(Point::zero(), display),
+            (Fit::Contain, true) | (Fit::Cover, false) => {
+                (display_width, source_height * display_width / source_width)
+            }
+            (Fit::Contain, false) | (Fit::Cover, true) => (
+                source_width * display_height / source_height,
+                display_height,
+            ),
+        };
+        // With `Fit::Cover` these can be bigger than the display, which in extreme
+        // cases could be too big for a `u32`.
+        let size = Size::new(
+            size.0.min(u32::MAX.into()) as u32,
+            size.1.min(u32::MAX.into()) as u32,
+        );
+        let top_left = Point::new(
+            ((i64::from(display.width) - i64::from(size.width)) / 2) as i32,
+            ((i64::from(display.height) - i64::from(size.height)) / 2) as i32,
+        );
+        Rectangle::new(top_left, size)
+    }
+}
+
+/// Sample the pixel of `source` (which is `source_size` in size) which shows up
+/// at `point` when it's scaled to `area` in size, using nearest-neighbour
+/// sampling: that is, the pixel whose center is closest.
+///
+/// `point` is relative to the top-left of the area, and has to be inside it.
+fn sample_nearest(source: &[Color], source_size: Size, area: Size, point: (u32, u32)) -> Color {
+    // Map the center of the destination pixel back into the image.
+    let sample = |offset: u32, source_len: u32, area_len: u32| {
+        let offset = u64::from(offset) * 2 + 1;
+        (offset * u64::from(source_len) / (u64::from(area_len) * 2)) as usize
+    };
+    let x = sample(point.0, source_size.width, area.width);
+    let y = sample(point.1, source_size.height, area.height);
+    source[y * source_size.width as usize + x]
+}
+
+/// Sample the pixel of `source` (which is `source_size` in size) which shows up
+/// at `point` when it's scaled to `area` in size, by averaging all the pixels
+/// it covers.
+///
+/// `point` is relative to the top-left of the area, and has to be inside it.
+/// Returns `None` if any of those pixels don't have a known RGB value.
+fn sample_box(source: &[Color], source_size: Size, area: Size, point: (u32, u32)) -> Option<Color> {
+    // Get the range of the image's pixels which the destination pixel covers along
+    // one axis. When scaling up, that's less than one pixel, so round it out to
+    // the one pixel it's inside.
+    let range = |offset: u32, source_len: u32, area_len: u32| {
+        let (source_len, area_len) = (u64::from(source_len), u64::from(area_len));
+        let offset = u64::from(offset);
+        let start = offset * source_len / area_len;
+        let end = ((offset + 1) * source_len).div_ceil(area_len);
+        start as usize..end.max(start + 1) as usize
+    };
+    let xs = range(point.0, source_size.width, area.width);
+    let ys = range(point.1, source_size.height, area.height);
+
+    let mut total = LinearRgb::BLACK;
+    let mut count = 0;
+    for y in ys {
+        let row = &source[y * source_size.width as usize..][xs.clone()];
+        for &color in row {
+            let color = LinearRgb::from_srgb(color.to_rgb888()?);
+            total = total.zip(color, |total, component| total + component);
+            count += 1;
+        }
+    }
+    Some(total.map(|total| total / count as f32).to_color())
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics_core::pixelcolor::Rgb888;
+    use embedded_graphics_core::prelude::*;
+
+    use super::Sampling;
+    use crate::Color;
+
+    #[test]
+    fn box_average_checkerboard() {
+        let (black, white) = (Color::Rgb(Rgb888::BLACK), Color::Rgb(Rgb888::WHITE));
+        let source: Vec<Color> = (0..4 * 2)
+            .map(|i| {
+                if (i % 4 + i / 4) % 2 == 0 {
+                    black
+                } else {
+                    white
+                }
+            })
+            .collect();
+        let (source_size, area) = (Size::new(4, 2), Size::new(2, 1));
+
+        // Each pixel covers two black and two white pixels, which average out to
+        // the midtone between them.
+        let midtone = Color::Rgb(Rgb888::new(188, 188, 188));
+        for x in 0..2 {
+            assert_eq!(
+                Sampling::BoxAverage.sample(&source, source_size, area, (x, 0)),
+                midtone
+            );
+            let nearest = Sampling::Nearest.sample(&source, source_size, area, (x, 0));
+            assert!(nearest == black || nearest == white);
+        }
+    }
+}
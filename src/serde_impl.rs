@@ -0,0 +1,147 @@
+use std::fmt;
+
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::prelude::*;
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Color;
+
+/// Colors are serialized as:
+///
+/// - The kebab-case name of the color for the named colors, e.g. `"red"` or
+///   `"dark-blue"`; this includes `"bg-color"`, `"fg-color"` and
+///   `"transparent"`.
+/// - A hex string like `"#ff8000"` for [`Color::Rgb`].
+/// - A map like `{ "rgba": [255, 128, 0, 64] }` for [`Color::Rgba`].
+/// - A map like `{ "ansi": 208 }` for [`Color::AnsiValue`].
+///
+/// This format won't change.
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Color::Rgb(rgb) => serializer.collect_str(&format_args!(
+                "#{:02x}{:02x}{:02x}",
+                rgb.r(),
+                rgb.g(),
+                rgb.b()
+            )),
+            Color::Rgba(rgb, alpha) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("rgba", &[rgb.r(), rgb.g(), rgb.b(), alpha])?;
+                map.end()
+            }
+            Color::AnsiValue(n) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("ansi", &n)?;
+                map.end()
+            }
+            color => {
+                // Every other variant is one of the named colors.
+                let name = color.name().expect("all other colors have names");
+                serializer.serialize_str(name)
+            }
+        }
+    }
+}
+
+/// Colors are deserialized from the same format they're serialized in.
+///
+/// Strings are parsed the same way as by [`Color::from_str`], so names are
+/// case-insensitive, and the other forms it allows are accepted too. A map
+/// like `{ "rgb": [255, 128, 0] }` is also accepted for [`Color::Rgb`].
+///
+/// Since a color can be either a string or a map, this has to look at the
+/// input to see which one it is, so it only works with self-describing
+/// formats like JSON, TOML or YAML. Formats which rely on the type to know
+/// what's coming next, like bincode, give an error.
+///
+/// [`Color::from_str`]: std::str::FromStr::from_str
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a color name, a hex color, or a map with an `rgb`, `rgba` or `ansi` key")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Color, E> {
+        s.parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(s), &self))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Color, A::Error> {
+        let key: String = map
+            .next_key()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let color = match key.as_str() {
+            "rgb" => {
+                let [r, g, b]: [u8; 3] = map.next_value()?;
+                Color::Rgb(Rgb888::new(r, g, b))
+            }
+            "rgba" => {
+                let [r, g, b, alpha]: [u8; 4] = map.next_value()?;
+                Color::Rgba(Rgb888::new(r, g, b), alpha)
+            }
+            "ansi" => Color::AnsiValue(map.next_value()?),
+            key => return Err(de::Error::unknown_field(key, &["rgb", "rgba", "ansi"])),
+        };
+        if map.next_key::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+        Ok(color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics_core::pixelcolor::Rgb888;
+
+    use crate::Color;
+
+    #[test]
+    fn json_round_trip() {
+        let colors = [
+            (Color::DarkBlue, r#""dark-blue""#),
+            (Color::Transparent, r#""transparent""#),
+            (Color::Rgb(Rgb888::new(255, 128, 0)), r##""#ff8000""##),
+            (
+                Color::Rgba(Rgb888::new(255, 128, 0), 64),
+                r#"{"rgba":[255,128,0,64]}"#,
+            ),
+            (Color::AnsiValue(208), r#"{"ansi":208}"#),
+        ];
+        for (color, json) in colors {
+            assert_eq!(serde_json::to_string(&color).unwrap(), json);
+            assert_eq!(serde_json::from_str::<Color>(json).unwrap(), color);
+        }
+    }
+
+    #[test]
+    fn json_other_forms() {
+        let parse = |json| serde_json::from_str::<Color>(json).ok();
+        assert_eq!(
+            parse(r#"{ "rgb": [255, 128, 0] }"#),
+            Some(Color::Rgb(Rgb888::new(255, 128, 0)))
+        );
+        assert_eq!(parse(r#""Dark-Blue""#), Some(Color::DarkBlue));
+        assert_eq!(
+            parse(r##""#f80""##),
+            Some(Color::Rgb(Rgb888::new(255, 136, 0)))
+        );
+
+        assert_eq!(parse(r#""not-a-color""#), None);
+        assert_eq!(parse(r#"{ "rgb": [255, 128] }"#), None);
+        assert_eq!(parse(r#"{ "hsl": [0, 0, 0] }"#), None);
+        assert_eq!(parse(r#"{ "ansi": 1, "rgb": [0, 0, 0] }"#), None);
+        assert_eq!(parse("{}"), None);
+    }
+}
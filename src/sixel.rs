@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::prelude::*;
+
+/// The most colors an image can use, since that's how many color registers
+/// terminals that support sixels usually have.
+const MAX_COLORS: usize = 256;
+
+/// Encodes images as sixels, keeping its buffers around between images so
+/// that their memory can be reused.
+#[derive(Debug, Default)]
+pub(crate) struct SixelEncoder {
+    /// The index into `palette` of the color of every pixel, in row-major
+    /// order.
+    indices: Vec<u8>,
+    palette: Vec<Rgb888>,
+    /// The index into `palette` of every color in it.
+    lookup: HashMap<Rgb888, u8>,
+    /// Space to build up each band of six rows in before writing it.
+    band: Vec<u8>,
+}
+
+impl SixelEncoder {
+    /// Write a sixel image `width` by `height` pixels to `out`, where
+    /// `pixel(x, y)` gives the color of each pixel.
+    ///
+    /// If the image has more colors than there are color registers, it gets
+    /// quantized to 3-3-2 bit RGB.
+    pub(crate) fn encode(
+        &mut self,
+        out: &mut impl Write,
+        width: usize,
+        height: usize,
+        pixel: impl Fn(usize, usize) -> Rgb888,
+    ) -> io::Result<()> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        self.index_pixels(width, height, pixel);
+
+        // Use square pixels, and leave any pixels we don't set alone, so that the
+        // last band doesn't cover up anything below the image if it's cut short.
+        write!(out, "\x1bP0;1;0q\"1;1;{width};{height}")?;
+        for (i, color) in self.palette.iter().enumerate() {
+            // Sixel colors are percentages.
+            let percent = |c: u8| (u32::from(c) * 100 + 127) / 255;
+            write!(
+                out,
+                "#{i};2;{};{};{}",
+                percent(color.r()),
+                percent(color.g()),
+                percent(color.b())
+            )?;
+        }
+
+        let indices = &self.indices;
+        let band = &mut self.band;
+        for y in (0..height).step_by(6) {
+            let rows = (height - y).min(6);
+            let pixels = &indices[y * width..(y + rows) * width];
+            let mut used = [false; MAX_COLORS];
+            for &index in pixels {
+                used[usize::from(index)] = true;
+            }
+
+            // Each band is drawn one color at a time, going back to the start of the band
+            // with `$` in between.
+            band.clear();
+            for color in (0..MAX_COLORS).filter(|&color| used[color]) {
+                if !band.is_empty() {
+                    band.push(b'$');
+                }
+                write!(band, "#{color}")?;
+                let sixel = |x: usize| {
+                    (0..rows).fold(0, |bits, dy| {
+                        bits | u8::from(usize::from(pixels[dy * width + x]) == color) << dy
+                    })
+                };
+                let mut x = 0;
+                while x < width {
+                    let bits = sixel(x);
+                    let mut end = x + 1;
+                    while end < width && sixel(end) == bits {
+                        end += 1;
+                    }
+                    // There's no need to write out the empty space at the end of the row.
+                    if bits == 0 && end == width {
+                        break;
+                    }
+                    write_run(band, bits, end - x)?;
+                    x = end;
+                }
+            }
+            // Moving down past the last band could make the terminal scroll.
+            if y + 6 < height {
+                band.push(b'-');
+            }
+            out.write_all(band)?;
+        }
+        out.write_all(b"\x1b\\")
+    }
+
+    /// Fill `indices` and `palette` with the colors of the image.
+    fn index_pixels(
+        &mut self,
+        width: usize,
+        height: usize,
+        pixel: impl Fn(usize, usize) -> Rgb888,
+    ) {
+        self.indices.clear();
+        self.palette.clear();
+        self.lookup.clear();
+        for y in 0..height {
+            for x in 0..width {
+                let color = pixel(x, y);
+                let index = match self.lookup.get(&color) {
+                    Some(&index) => index,
+                    None if self.palette.len() < MAX_COLORS => {
+                        let index = self.palette.len() as u8;
+                        self.palette.push(color);
+                        self.lookup.insert(color, index);
+                        index
+                    }
+                    None => {
+                        self.index_quantized(width, height, pixel);
+                        return;
+                    }
+                };
+                self.indices.push(index);
+            }
+        }
+    }
+
+    /// Fill `indices` and `palette` with the colors of the image quantized to
+    /// 3-3-2 bit RGB, for when it has too many colors to use them directly.
+    fn index_quantized(
+        &mut self,
+        width: usize,
+        height: usize,
+        pixel: impl Fn(usize, usize) -> Rgb888,
+    ) {
+        self.indices.clear();
+        self.palette.clear();
+        self.palette.extend((0..MAX_COLORS as u32).map(|i| {
+            let (r, g, b) = (i >> 5, (i >> 2) & 0b111, i & 0b11);
+            Rgb888::new(
+                (r * 255 / 7) as u8,
+                (g * 255 / 7) as u8,
+                (b * 255 / 3) as u8,
+            )
+        }));
+        for y in 0..height {
+            for x in 0..width {
+                let color = pixel(x, y);
+                self.indices
+                    .push((color.r() & 0b11100000) | (color.g() >> 5) << 2 | color.b() >> 6);
+            }
+        }
+    }
+}
+
+/// Write `count` copies of the sixel with the given bits set to `out`.
+fn write_run(out: &mut Vec<u8>, bits: u8, count: usize) -> io::Result<()> {
+    let sixel = b'?' + bits;
+    // Repeating a sixel takes at least 3 bytes, so it's only worth it for runs of
+    // more than 3.
+    if count > 3 {
+        write!(out, "!{count}")?;
+        out.push(sixel);
+    } else {
+        out.extend(std::iter::repeat_n(sixel, count));
+    }
+    Ok(())
+}
+
+/// Guess whether the terminal supports sixels, from the values of the `TERM`
+/// and `TERM_PROGRAM` environment variables.
+pub(crate) fn supported(term: Option<&str>, term_program: Option<&str>) -> bool {
+    // These all support sixels, and set one of those to something specific to them.
+    const TERMS: [&str; 4] = ["foot", "mlterm", "yaft", "contour"];
+    const PROGRAMS: [&str; 3] = ["WezTerm", "iTerm.app", "mintty"];
+    term.is_some_and(|term| TERMS.iter().any(|prefix| term.starts_with(prefix)))
+        || term_program.is_some_and(|program| PROGRAMS.contains(&program))
+}
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+use embedded_graphics_core::prelude::*;
+
+use crate::cell::TextCell;
+use crate::{Attributes, Color};
+
+/// A copy of everything drawn to a [`TerminalDisplay`], taken with
+/// [`TerminalDisplay::snapshot`], which can be put back later with
+/// [`TerminalDisplay::restore_snapshot`].
+///
+/// This includes text written with [`TerminalDisplay::put_str`] and
+/// attributes set with [`TerminalDisplay::set_attr`], but not any of the
+/// display's settings.
+///
+/// [`TerminalDisplay`]: crate::TerminalDisplay
+/// [`TerminalDisplay::snapshot`]: crate::TerminalDisplay::snapshot
+/// [`TerminalDisplay::restore_snapshot`]: crate::TerminalDisplay::restore_snapshot
+/// [`TerminalDisplay::put_str`]: crate::TerminalDisplay::put_str
+/// [`TerminalDisplay::set_attr`]: crate::TerminalDisplay::set_attr
+#[derive(Clone, Debug)]
+pub struct BufferSnapshot {
+    pub(crate) buffer: Vec<Vec<Color>>,
+    pub(crate) text: HashMap<(u16, u16), TextCell>,
+    pub(crate) attributes: HashMap<(u16, u16), Attributes>,
+}
+
+impl BufferSnapshot {
+    /// Get the size of the display in pixels when the snapshot was taken.
+    pub fn size(&self) -> Size {
+        let width = self.buffer.first().map_or(0, |row| row.len());
+        Size::new(width as u32, self.buffer.len() as u32)
+    }
+}
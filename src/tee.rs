@@ -0,0 +1,52 @@
+use std::io::{self, Write};
+
+/// A writer which writes everything to two other writers, e.g. so that what's
+/// drawn to the terminal can be logged to a file at the same time.
+///
+/// Everything is written in full to both writers, so they always get the same
+/// bytes. If one of them fails, the other one is still written to, and then
+/// the first error is returned.
+#[derive(Debug)]
+pub struct Tee<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Write, B: Write> Tee<A, B> {
+    /// Create a writer which writes to both `first` and `second`, in that
+    /// order.
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+
+    /// Get references to the two writers being written to.
+    pub fn get_ref(&self) -> (&A, &B) {
+        (&self.first, &self.second)
+    }
+
+    /// Give back the two writers being written to.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Writing only part of `buf` could mean writing different amounts to each
+        // writer, so it all gets written at once.
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let first = self.first.write_all(buf);
+        let second = self.second.write_all(buf);
+        first.and(second)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let first = self.first.flush();
+        let second = self.second.flush();
+        first.and(second)
+    }
+}
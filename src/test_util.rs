@@ -0,0 +1,82 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::tty::SizeSource;
+use crate::{TerminalDisplay, TerminalDisplayBuilder};
+
+/// A writer which can still be read from after it's been given to a display.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SharedWriter(Rc<RefCell<Vec<u8>>>);
+
+impl SharedWriter {
+    /// Take everything that's been written so far.
+    pub(crate) fn take(&self) -> String {
+        String::from_utf8(self.0.take()).unwrap()
+    }
+}
+
+impl Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Build a display on a pretend terminal with the given number of columns
+/// and rows, along with a handle to what it writes.
+///
+/// Whatever building the display writes is thrown away.
+pub(crate) fn display(
+    builder: TerminalDisplayBuilder,
+    columns: u16,
+    rows: u16,
+) -> (TerminalDisplay<SharedWriter>, SharedWriter) {
+    let out = SharedWriter::default();
+    let display = builder
+        .build_with(out.clone(), SizeSource::Fixed(columns, rows))
+        .unwrap();
+    out.take();
+    (display, out)
+}
+
+/// An allocator which counts how many allocations each thread makes, so that
+/// tests can check that something doesn't allocate.
+///
+/// The counts are per thread, so that tests running at the same time don't
+/// get counted.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// SAFETY: this just passes everything through to the system allocator.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Get the number of allocations the current thread has made so far.
+pub(crate) fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
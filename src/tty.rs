@@ -0,0 +1,62 @@
+use std::fs::File;
+use std::io;
+
+use crossterm::terminal;
+
+/// Where to get the size of the terminal from.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum SizeSource {
+    /// The controlling terminal of the process, which is what crossterm uses.
+    Controlling,
+    /// The terminal device open as this file descriptor.
+    #[cfg(unix)]
+    Fd(std::os::unix::io::RawFd),
+    /// A terminal which is always the given number of (columns, rows), for
+    /// testing without a real one.
+    #[cfg(test)]
+    Fixed(u16, u16),
+}
+
+impl SizeSource {
+    /// Get the size of the terminal from the terminal device `file`, falling
+    /// back to the controlling terminal on platforms where that isn't
+    /// possible.
+    ///
+    /// The file has to stay open for as long as this is used.
+    pub(crate) fn for_file(file: &File) -> SizeSource {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            SizeSource::Fd(file.as_raw_fd())
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = file;
+            SizeSource::Controlling
+        }
+    }
+
+    /// Get the size of the terminal as (columns, rows).
+    pub(crate) fn size(self) -> io::Result<(u16, u16)> {
+        match self {
+            SizeSource::Controlling => terminal::size(),
+            #[cfg(unix)]
+            SizeSource::Fd(fd) => {
+                let mut size = libc::winsize {
+                    ws_row: 0,
+                    ws_col: 0,
+                    ws_xpixel: 0,
+                    ws_ypixel: 0,
+                };
+                // SAFETY: `TIOCGWINSZ` only writes a `winsize` to the pointer it's given,
+                // and an invalid file descriptor just makes it fail.
+                if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok((size.ws_col, size.ws_row))
+            }
+            #[cfg(test)]
+            SizeSource::Fixed(columns, rows) => Ok((columns, rows)),
+        }
+    }
+}
@@ -0,0 +1,808 @@
+use std::io::{self, Write};
+
+use crossterm::style::Color as CrosstermColor;
+use crossterm::{cursor, style, terminal, QueueableCommand};
+#[cfg(feature = "sixel")]
+use embedded_graphics_core::pixelcolor::Rgb888;
+
+use crate::render::{self, RenderConfig, RenderMode};
+#[cfg(feature = "sixel")]
+use crate::sixel::SixelEncoder;
+use crate::{Attributes, Color, ColorMode};
+
+/// Keeps track of where the terminal's cursor is, so that we don't have to move
+/// it when it's already in the right place.
+#[derive(Default)]
+struct CursorTracker {
+    /// Where we expect the cursor to be as (column, row), or `None` if we don't
+    /// know.
+    position: Option<(u16, u16)>,
+    /// The number of times the cursor has actually been moved.
+    moves: usize,
+}
+
+impl CursorTracker {
+    /// Move the cursor to the given cell, unless it's already there.
+    fn move_to(&mut self, mut stdout: impl Write, column: u16, row: u16) -> io::Result<()> {
+        if self.position != Some((column, row)) {
+            stdout.queue(cursor::MoveTo(column, row))?;
+            self.position = Some((column, row));
+            self.moves += 1;
+        }
+        Ok(())
+    }
+
+    /// Record that `count` cells have just been written, which moves the
+    /// cursor that many columns to the right.
+    ///
+    /// If that goes past the last column, the cursor ends up past the edge of
+    /// the terminal, where we'll never expect it to be; so it still gets moved
+    /// explicitly next time.
+    fn advance(&mut self, count: u16) {
+        if let Some((column, _)) = &mut self.position {
+            *column = column.saturating_add(count);
+        }
+    }
+
+    /// Forget where the cursor is, so that it has to be moved explicitly next
+    /// time.
+    fn reset(&mut self) {
+        self.position = None;
+    }
+}
+
+/// A writer which throws away everything written to it, and just counts how
+/// many bytes there were.
+#[derive(Default)]
+pub(crate) struct ByteCount(pub(crate) usize);
+
+impl Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A wrapper around a writer which counts how many bytes have been written to
+/// it.
+struct Counted<W> {
+    inner: W,
+    bytes: usize,
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Running totals of what a [`TerminalWriter`] has written, which can be
+/// compared from before and after writing something to see what it took.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct WriteCounts {
+    pub(crate) bytes: usize,
+    /// The number of times the foreground or background color has been set;
+    /// setting both at once counts twice.
+    pub(crate) color_changes: usize,
+    pub(crate) cursor_moves: usize,
+}
+
+impl WriteCounts {
+    /// Get how much more has been written in these counts than in `earlier`.
+    pub(crate) fn since(self, earlier: WriteCounts) -> WriteCounts {
+        WriteCounts {
+            bytes: self.bytes - earlier.bytes,
+            color_changes: self.color_changes - earlier.color_changes,
+            cursor_moves: self.cursor_moves - earlier.cursor_moves,
+        }
+    }
+}
+
+/// A wrapper around a writer to a terminal which keeps track of the terminal's
+/// state, so that we can avoid writing redundant commands.
+pub(crate) struct TerminalWriter<W> {
+    /// The underlying writer, or `None` if it's been taken back out with
+    /// [`TerminalWriter::take_writer`].
+    writer: Option<Counted<W>>,
+    /// The number of times the foreground or background color has been set.
+    color_changes: usize,
+    /// The foreground color we last set the terminal to, or `None` if we don't
+    /// know what it is.
+    current_fg: Option<CrosstermColor>,
+    /// The background color we last set the terminal to, or `None` if we don't
+    /// know what it is.
+    current_bg: Option<CrosstermColor>,
+    /// The text attributes we last set the terminal to.
+    ///
+    /// These are always turned back off at the end of a flush, so unlike the
+    /// colors, we never need to forget what they are.
+    current_attributes: Attributes,
+    cursor: CursorTracker,
+    /// Space to build up runs of characters in before writing them.
+    scratch: String,
+    #[cfg(feature = "sixel")]
+    sixel: SixelEncoder,
+    /// Whether to assume that every glyph takes up exactly one column, so that
+    /// the cursor can be left to move along by itself after writing a cell.
+    pub(crate) assume_narrow_glyphs: bool,
+    /// Whether to set colors using the original SGR codes for the 8 basic
+    /// colors (30–37 and 40–47), for terminals which don't support anything
+    /// else.
+    basic_colors: bool,
+    /// Whether to wrap each update in the synchronized output sequences.
+    pub(crate) synchronized_output: bool,
+    /// Whether we've started a synchronized update which hasn't been finished
+    /// yet.
+    in_update: bool,
+    /// Whether the cursor's currently hidden.
+    cursor_hidden: bool,
+    /// Whether the cursor should be left hidden when restoring the terminal,
+    /// if it's been asked for with [`TerminalWriter::request_cursor_hidden`].
+    ///
+    /// Otherwise, the cursor gets shown again if we've hidden it, since that's
+    /// how it was before we started.
+    requested_cursor_hidden: Option<bool>,
+    /// Whether we've set the colors to something other than the terminal's
+    /// defaults, and so need to reset them when restoring the terminal.
+    colors_modified: bool,
+    /// Whether we've switched to the alternate screen, and so need to switch
+    /// back when restoring the terminal.
+    alternate_screen: bool,
+    /// Whether we've enabled raw mode, and so need to disable it when
+    /// restoring the terminal.
+    raw_mode: bool,
+    /// The changes to the terminal's state which were undone by
+    /// [`TerminalWriter::suspend`], or `None` if it isn't suspended.
+    suspended: Option<Suspended>,
+}
+
+/// The changes to the terminal's state which [`TerminalWriter::suspend`]
+/// undoes, so that [`TerminalWriter::resume`] knows which ones to redo.
+#[derive(Copy, Clone, Debug)]
+struct Suspended {
+    cursor_hidden: bool,
+    alternate_screen: bool,
+    raw_mode: bool,
+}
+
+impl<W: Write> TerminalWriter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer: Some(Counted {
+                inner: writer,
+                bytes: 0,
+            }),
+            color_changes: 0,
+            current_fg: None,
+            current_bg: None,
+            current_attributes: Attributes::NONE,
+            cursor: CursorTracker::default(),
+            scratch: String::new(),
+            #[cfg(feature = "sixel")]
+            sixel: SixelEncoder::default(),
+            assume_narrow_glyphs: true,
+            basic_colors: false,
+            synchronized_output: false,
+            in_update: false,
+            cursor_hidden: false,
+            requested_cursor_hidden: None,
+            colors_modified: false,
+            alternate_screen: false,
+            raw_mode: false,
+            suspended: None,
+        }
+    }
+
+    /// Make a writer to `writer` which starts out knowing the same things
+    /// about the terminal's state as this one, so that it writes exactly what
+    /// this one would.
+    pub(crate) fn fork<X>(&self, writer: X) -> TerminalWriter<X> {
+        TerminalWriter {
+            writer: Some(Counted {
+                inner: writer,
+                bytes: 0,
+            }),
+            color_changes: 0,
+            current_fg: self.current_fg,
+            current_bg: self.current_bg,
+            current_attributes: self.current_attributes,
+            cursor: CursorTracker {
+                position: self.cursor.position,
+                moves: 0,
+            },
+            scratch: String::new(),
+            #[cfg(feature = "sixel")]
+            sixel: SixelEncoder::default(),
+            assume_narrow_glyphs: self.assume_narrow_glyphs,
+            basic_colors: self.basic_colors,
+            synchronized_output: self.synchronized_output,
+            in_update: self.in_update,
+            cursor_hidden: self.cursor_hidden,
+            requested_cursor_hidden: self.requested_cursor_hidden,
+            colors_modified: self.colors_modified,
+            alternate_screen: self.alternate_screen,
+            raw_mode: self.raw_mode,
+            suspended: self.suspended,
+        }
+    }
+
+    /// Take the underlying writer back out.
+    ///
+    /// Nothing else can be written afterwards.
+    pub(crate) fn take_writer(&mut self) -> W {
+        self.writer.take().expect("writer already taken").inner
+    }
+
+    /// Check whether the underlying writer is still here, and hasn't been
+    /// taken back out with [`TerminalWriter::take_writer`].
+    pub(crate) fn has_writer(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Get the underlying writer.
+    fn writer(&mut self) -> &mut Counted<W> {
+        self.writer.as_mut().expect("writer already taken")
+    }
+
+    /// Get the totals of everything that's been written so far.
+    pub(crate) fn counts(&self) -> WriteCounts {
+        WriteCounts {
+            bytes: self.writer.as_ref().map_or(0, |writer| writer.bytes),
+            color_changes: self.color_changes,
+            cursor_moves: self.cursor.moves,
+        }
+    }
+
+    /// Forget everything we know about the terminal's state, so that it all
+    /// gets set explicitly next time.
+    pub(crate) fn reset(&mut self) {
+        self.current_fg = None;
+        self.current_bg = None;
+        self.cursor.reset();
+    }
+
+    /// Move the cursor to the given cell, unless it's already there.
+    pub(crate) fn move_to(&mut self, column: u16, row: u16) -> io::Result<()> {
+        let writer = self.writer.as_mut().expect("writer already taken");
+        self.cursor.move_to(writer, column, row)
+    }
+
+    /// Draw a sixel image `width` by `height` pixels with its top-left corner
+    /// at the cursor, where `pixel(x, y)` gives the color of each pixel.
+    #[cfg(feature = "sixel")]
+    pub(crate) fn write_sixel(
+        &mut self,
+        width: usize,
+        height: usize,
+        pixel: impl Fn(usize, usize) -> Rgb888,
+    ) -> io::Result<()> {
+        let writer = self.writer.as_mut().expect("writer already taken");
+        self.sixel.encode(writer, width, height, pixel)?;
+        // Terminals don't agree on where the cursor ends up afterwards.
+        self.cursor.reset();
+        Ok(())
+    }
+
+    /// Show or hide the cursor, unless it's already shown or hidden.
+    ///
+    /// While the terminal's suspended, this only changes whether the cursor
+    /// gets hidden again when it's resumed.
+    pub(crate) fn set_cursor_hidden(&mut self, hidden: bool) -> io::Result<()> {
+        if let Some(suspended) = &mut self.suspended {
+            suspended.cursor_hidden = hidden;
+            return Ok(());
+        }
+        if self.cursor_hidden == hidden {
+            return Ok(());
+        }
+        if hidden {
+            self.writer().queue(cursor::Hide)?;
+        } else {
+            self.writer().queue(cursor::Show)?;
+        }
+        self.cursor_hidden = hidden;
+        Ok(())
+    }
+
+    /// Show or hide the cursor like [`TerminalWriter::set_cursor_hidden`],
+    /// because the user asked for it, so that it's left that way when the
+    /// terminal is restored.
+    pub(crate) fn request_cursor_hidden(&mut self, hidden: bool) -> io::Result<()> {
+        self.requested_cursor_hidden = Some(hidden);
+        self.set_cursor_hidden(hidden)
+    }
+
+    /// Tell the terminal to start holding off on showing what we write until
+    /// [`TerminalWriter::finish_update`] is called, if synchronized output is
+    /// enabled and we haven't already.
+    pub(crate) fn start_update(&mut self) -> io::Result<()> {
+        if self.synchronized_output && !self.in_update {
+            self.writer().write_all(b"\x1b[?2026h")?;
+            self.in_update = true;
+        }
+        Ok(())
+    }
+
+    /// Tell the terminal to show everything written since
+    /// [`TerminalWriter::start_update`] was called, if it was.
+    pub(crate) fn finish_update(&mut self) -> io::Result<()> {
+        if self.in_update {
+            self.writer().write_all(b"\x1b[?2026l")?;
+            self.in_update = false;
+        }
+        Ok(())
+    }
+
+    /// Clear the whole terminal to its default background color.
+    pub(crate) fn clear_screen(&mut self) -> io::Result<()> {
+        self.set_attributes(Attributes::NONE)?;
+        self.set_colors(Some(CrosstermColor::Reset), Some(CrosstermColor::Reset))?;
+        self.writer()
+            .queue(terminal::Clear(terminal::ClearType::All))?;
+        Ok(())
+    }
+
+    /// Switch to the alternate screen.
+    pub(crate) fn enter_alternate_screen(&mut self) -> io::Result<()> {
+        self.writer().queue(terminal::EnterAlternateScreen)?;
+        self.alternate_screen = true;
+        // The alternate screen has its own cursor position.
+        self.cursor.reset();
+        self.flush()
+    }
+
+    /// Enable raw mode.
+    pub(crate) fn enable_raw_mode(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        self.raw_mode = true;
+        Ok(())
+    }
+
+    /// Undo all the changes we've made to the terminal's state, and flush.
+    ///
+    /// The cursor is left the way the user last asked for it with
+    /// [`TerminalWriter::request_cursor_hidden`], or shown if they haven't.
+    /// Once the writer's been taken, there's nothing left to restore.
+    pub(crate) fn restore(&mut self) -> io::Result<()> {
+        self.restore_with_cursor(self.requested_cursor_hidden.unwrap_or(false))
+    }
+
+    /// Undo all the changes we've made to the terminal's state, leaving the
+    /// cursor hidden or not according to `cursor_hidden`, and flush.
+    fn restore_with_cursor(&mut self, cursor_hidden: bool) -> io::Result<()> {
+        if self.writer.is_none() {
+            return Ok(());
+        }
+        // Otherwise, the terminal would stop showing anything until it gave up waiting
+        // for the update to finish.
+        self.finish_update()?;
+        self.set_cursor_hidden(cursor_hidden)?;
+        self.set_attributes(Attributes::NONE)?;
+        if self.colors_modified {
+            self.set_colors(Some(CrosstermColor::Reset), Some(CrosstermColor::Reset))?;
+            self.colors_modified = false;
+        }
+        if self.alternate_screen {
+            self.writer().queue(terminal::LeaveAlternateScreen)?;
+            self.alternate_screen = false;
+            self.cursor.reset();
+        }
+        self.flush()?;
+
+        if self.raw_mode {
+            terminal::disable_raw_mode()?;
+            self.raw_mode = false;
+        }
+        Ok(())
+    }
+
+    /// Undo all the changes we've made to the terminal's state, like
+    /// [`TerminalWriter::restore`], but remember them so that they can be
+    /// made again by [`TerminalWriter::resume`].
+    ///
+    /// Does nothing if the terminal's already suspended.
+    pub(crate) fn suspend(&mut self) -> io::Result<()> {
+        if self.suspended.is_some() {
+            return Ok(());
+        }
+        let suspended = Suspended {
+            cursor_hidden: self.cursor_hidden,
+            alternate_screen: self.alternate_screen,
+            raw_mode: self.raw_mode,
+        };
+        // Whatever runs in the meantime will want to see where the cursor is.
+        self.restore_with_cursor(false)?;
+        self.suspended = Some(suspended);
+        Ok(())
+    }
+
+    /// Redo the changes to the terminal's state which were undone by
+    /// [`TerminalWriter::suspend`], and flush.
+    ///
+    /// Does nothing if the terminal isn't suspended.
+    pub(crate) fn resume(&mut self) -> io::Result<()> {
+        let Some(suspended) = self.suspended.take() else {
+            return Ok(());
+        };
+        // Whatever ran in the meantime could have left the terminal in any state.
+        self.reset();
+        if suspended.alternate_screen {
+            self.enter_alternate_screen()?;
+        }
+        if suspended.raw_mode {
+            self.enable_raw_mode()?;
+        }
+        if suspended.cursor_hidden {
+            self.set_cursor_hidden(true)?;
+        }
+        self.flush()
+    }
+
+    /// Whether the terminal's been suspended with [`TerminalWriter::suspend`]
+    /// and not resumed yet.
+    pub(crate) fn suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    /// Set the terminal's foreground and background colors, only queueing
+    /// commands for the ones which are different to what they're already set
+    /// to.
+    ///
+    /// Passing `None` leaves that color as it is.
+    fn set_colors(
+        &mut self,
+        fg: Option<CrosstermColor>,
+        bg: Option<CrosstermColor>,
+    ) -> io::Result<()> {
+        if let Some(fg) = fg {
+            if self.current_fg != Some(fg) {
+                match basic_color_code(fg) {
+                    Some(code) if self.basic_colors => write!(self.writer(), "\x1b[3{code}m")?,
+                    _ => {
+                        self.writer().queue(style::SetForegroundColor(fg))?;
+                    }
+                }
+                self.current_fg = Some(fg);
+                self.color_changes += 1;
+                self.colors_modified |= fg != CrosstermColor::Reset;
+            }
+        }
+        if let Some(bg) = bg {
+            if self.current_bg != Some(bg) {
+                match basic_color_code(bg) {
+                    Some(code) if self.basic_colors => write!(self.writer(), "\x1b[4{code}m")?,
+                    _ => {
+                        self.writer().queue(style::SetBackgroundColor(bg))?;
+                    }
+                }
+                self.current_bg = Some(bg);
+                self.color_changes += 1;
+                self.colors_modified |= bg != CrosstermColor::Reset;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the terminal's text attributes, only queueing commands for the ones
+    /// which are being turned on or off.
+    ///
+    /// Each attribute is turned off on its own, rather than resetting
+    /// everything, so that the colors are left alone.
+    pub(crate) fn set_attributes(&mut self, attributes: Attributes) -> io::Result<()> {
+        let current = self.current_attributes;
+        for attribute in current.iter() {
+            if !attributes.contains(attribute) {
+                let (_, off) = crossterm_attributes(attribute);
+                self.writer().queue(style::SetAttribute(off))?;
+            }
+        }
+        for attribute in attributes.iter() {
+            if !current.contains(attribute) {
+                let (on, _) = crossterm_attributes(attribute);
+                self.writer().queue(style::SetAttribute(on))?;
+            }
+        }
+        self.current_attributes = attributes;
+        Ok(())
+    }
+
+    /// Write `count` copies of a cell at the current cursor position.
+    ///
+    /// `pixels` are the colors of the pixels within the cell in row-major
+    /// order.
+    pub(crate) fn write_cells(
+        &mut self,
+        config: &RenderConfig,
+        pixels: &[Color],
+        count: u16,
+    ) -> io::Result<()> {
+        self.basic_colors = config.color_mode == ColorMode::Ansi8;
+        let glyphs = config.glyphs();
+        // Cells which haven't been drawn in get the glyph for empty cells, if there is one.
+        let empty = config.display_color(Color::BgColor);
+        let empty_cell = config
+            .empty_cell
+            .filter(|_| pixels.iter().all(|&pixel| pixel == empty));
+        let glyph = match (empty_cell, config.render_mode) {
+            (Some((glyph, color)), _) => {
+                self.set_colors(
+                    Some(config.display_color(color).to_crossterm_color()),
+                    Some(empty.to_crossterm_color()),
+                )?;
+                glyph
+            }
+            (None, RenderMode::HalfBlock) => {
+                self.prepare_half_block(pixels[0], pixels[1], glyphs)?
+            }
+            // All of these are one color over the background, with each pixel either on
+            // or off.
+            (None, RenderMode::Braille | RenderMode::VerticalBars | RenderMode::HorizontalBars) => {
+                self.prepare_braille(pixels, empty, glyphs)?
+            }
+            (None, RenderMode::Quadrant | RenderMode::Sextant) => {
+                self.prepare_two_color(pixels, glyphs)?
+            }
+            (None, RenderMode::FullCell) => self.prepare_full_cell(pixels[0], glyphs)?,
+        };
+
+        if !self.assume_narrow_glyphs {
+            // We can't trust the terminal to move the cursor along by exactly one column,
+            // so move it to every cell explicitly.
+            let start = self.cursor.position;
+            for i in 0..count {
+                if let (Some((column, row)), true) = (start, i > 0) {
+                    self.move_to(column.saturating_add(i), row)?;
+                }
+                let mut buf = [0; 4];
+                self.writer()
+                    .write_all(glyph.encode_utf8(&mut buf).as_bytes())?;
+                self.cursor.reset();
+            }
+            return Ok(());
+        }
+
+        // Write the whole run at once, rather than a character at a time.
+        self.cursor.advance(count);
+        self.scratch.clear();
+        self.scratch
+            .extend(std::iter::repeat_n(glyph, count.into()));
+        let writer = self.writer.as_mut().expect("writer already taken");
+        writer.write_all(self.scratch.as_bytes())
+    }
+
+    /// Write a text cell at the current cursor position.
+    pub(crate) fn write_text(
+        &mut self,
+        config: &RenderConfig,
+        character: char,
+        fg: Color,
+        bg: Color,
+    ) -> io::Result<()> {
+        self.basic_colors = config.color_mode == ColorMode::Ansi8;
+        let fg = config.display_color(fg.blend_over(Color::BgColor));
+        let bg = config.display_color(bg.blend_over(Color::BgColor));
+        self.set_colors(Some(fg.to_crossterm_color()), Some(bg.to_crossterm_color()))?;
+
+        let mut buf = [0; 4];
+        self.writer()
+            .write_all(character.encode_utf8(&mut buf).as_bytes())?;
+        if self.assume_narrow_glyphs {
+            self.cursor.advance(1);
+        } else {
+            self.cursor.reset();
+        }
+        Ok(())
+    }
+
+    /// Set the colors for a cell in [`RenderMode::HalfBlock`], and return the
+    /// character to draw it with.
+    fn prepare_half_block(
+        &mut self,
+        top_color: Color,
+        bottom_color: Color,
+        glyphs: fn(u8) -> char,
+    ) -> io::Result<char> {
+        // Both of the terminal's default colors get set using `Reset`, which means
+        // different things for the foreground and background; so the orientation
+        // has to be picked so that `BgColor` only ever ends up as the background, and
+        // `FgColor` only ever ends up as the foreground. That way, any half which is
+        // left blank shows the terminal theme's background rather than some
+        // concrete color.
+        match (top_color, bottom_color) {
+            (Color::BgColor, Color::BgColor) => {
+                self.set_colors(None, Some(CrosstermColor::Reset))?;
+                Ok(glyphs(0b00))
+            }
+            (Color::FgColor, Color::FgColor) => {
+                self.set_colors(Some(CrosstermColor::Reset), None)?;
+                Ok(glyphs(0b11))
+            }
+            // The top half can be the background, and the bottom half the foreground.
+            (top_color, bottom_color)
+                if top_color != Color::FgColor && bottom_color != Color::BgColor =>
+            {
+                self.set_colors(
+                    Some(bottom_color.to_crossterm_color()),
+                    Some(top_color.to_crossterm_color()),
+                )?;
+                Ok(glyphs(0b10))
+            }
+            // Otherwise, either the top half is `FgColor` or the bottom half is
+            // `BgColor`, and drawing the top half as the foreground puts them where they
+            // belong.
+            (top_color, bottom_color) => {
+                self.set_colors(
+                    Some(top_color.to_crossterm_color()),
+                    Some(bottom_color.to_crossterm_color()),
+                )?;
+                Ok(glyphs(0b01))
+            }
+        }
+    }
+
+    /// Set the colors for a cell in [`RenderMode::FullCell`], and return the
+    /// character to draw it with.
+    fn prepare_full_cell(&mut self, color: Color, glyphs: fn(u8) -> char) -> io::Result<char> {
+        match color {
+            // The terminal's default foreground color can only be set as the foreground.
+            Color::FgColor => {
+                self.set_colors(Some(CrosstermColor::Reset), None)?;
+                Ok(glyphs(1))
+            }
+            // Everything else can just be the background of an empty cell, which doesn't
+            // depend on the font at all.
+            color => {
+                self.set_colors(None, Some(color.to_crossterm_color()))?;
+                Ok(glyphs(0))
+            }
+        }
+    }
+
+    /// Set the colors for a cell which is made up of several two-color pixels,
+    /// and return the character to draw it with, using `glyphs` to get the
+    /// character for a mask of which pixels are the foreground color.
+    fn prepare_two_color(&mut self, pixels: &[Color], glyphs: fn(u8) -> char) -> io::Result<char> {
+        let (fg, bg, mask) = render::two_colors(pixels);
+        self.set_colors(
+            fg.map(Color::to_crossterm_color),
+            bg.map(Color::to_crossterm_color),
+        )?;
+        Ok(glyphs(mask))
+    }
+
+    /// Set the colors for a cell in [`RenderMode::Braille`],
+    /// [`RenderMode::VerticalBars`] or [`RenderMode::HorizontalBars`], and
+    /// return the character to draw it with.
+    ///
+    /// `empty` is what [`Color::BgColor`] is displayed as, which is the color
+    /// of the pixels that are off, and the background of the cell.
+    fn prepare_braille(
+        &mut self,
+        pixels: &[Color],
+        empty: Color,
+        glyphs: fn(u8) -> char,
+    ) -> io::Result<char> {
+        let mut dots = 0;
+        let mut on_colors = [Color::BgColor; 8];
+        let mut on_count = 0;
+        for (i, &color) in pixels.iter().enumerate() {
+            if color != empty {
+                dots |= 1 << i;
+                on_colors[on_count] = color;
+                on_count += 1;
+            }
+        }
+
+        match render::most_common(&on_colors[..on_count]) {
+            Some(color) => {
+                self.set_colors(
+                    Some(color.to_crossterm_color()),
+                    Some(empty.to_crossterm_color()),
+                )?;
+                Ok(glyphs(dots))
+            }
+            None => {
+                self.set_colors(None, Some(empty.to_crossterm_color()))?;
+                Ok(' ')
+            }
+        }
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+/// Get the crossterm attributes for turning a single one of our attributes on
+/// and off, as (on, off).
+fn crossterm_attributes(attribute: Attributes) -> (style::Attribute, style::Attribute) {
+    match attribute {
+        Attributes::BOLD => (style::Attribute::Bold, style::Attribute::NormalIntensity),
+        Attributes::BLINK => (style::Attribute::SlowBlink, style::Attribute::NoBlink),
+        Attributes::UNDERLINE => (style::Attribute::Underlined, style::Attribute::NoUnderline),
+        Attributes::REVERSE => (style::Attribute::Reverse, style::Attribute::NoReverse),
+        _ => unreachable!("not a single attribute: {attribute:?}"),
+    }
+}
+
+/// Get the digit which follows the 3 or 4 in the original SGR codes for setting
+/// the foreground or background to `color`, if it's one of the 8 basic colors
+/// or the terminal's default.
+///
+/// crossterm always sets named colors using the 256-color codes, which
+/// terminals that only support the basic colors don't understand.
+fn basic_color_code(color: CrosstermColor) -> Option<u8> {
+    let code = match color {
+        CrosstermColor::Black => 0,
+        CrosstermColor::DarkRed => 1,
+        CrosstermColor::DarkGreen => 2,
+        CrosstermColor::DarkYellow => 3,
+        CrosstermColor::DarkBlue => 4,
+        CrosstermColor::DarkMagenta => 5,
+        CrosstermColor::DarkCyan => 6,
+        CrosstermColor::Grey => 7,
+        CrosstermColor::Reset => 9,
+        _ => return None,
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics_core::prelude::*;
+
+    use super::TerminalWriter;
+    use crate::render::RenderConfig;
+    use crate::test_util;
+    use crate::{Color, RenderMode, TerminalDisplay};
+
+    /// Get what's written to draw a single half block cell with the given top
+    /// and bottom colors.
+    fn half_block(top: Color, bottom: Color) -> String {
+        let mut out = TerminalWriter::new(Vec::new());
+        out.write_cells(&RenderConfig::default(), &[top, bottom], 1)
+            .unwrap();
+        String::from_utf8(out.take_writer()).unwrap()
+    }
+
+    #[test]
+    fn half_block_combinations() {
+        // The terminal's default colors only ever get used for the halves they belong
+        // in: `BgColor` as the background, and `FgColor` as the foreground.
+        assert_eq!(half_block(Color::BgColor, Color::BgColor), "\x1b[49m ");
+        assert_eq!(
+            half_block(Color::FgColor, Color::BgColor),
+            "\x1b[39m\x1b[49m▀"
+        );
+        assert_eq!(
+            half_block(Color::Red, Color::BgColor),
+            "\x1b[38;5;9m\x1b[49m▀"
+        );
+        assert_eq!(
+            half_block(Color::BgColor, Color::Red),
+            "\x1b[38;5;9m\x1b[49m▄"
+        );
+    }
+
+    #[test]
+    fn braille_dots_ignore_canvas() {
+        let builder = TerminalDisplay::builder()
+            .render_mode(RenderMode::Braille)
+            .canvas_color(Color::Blue);
+        let (mut display, out) = test_util::display(builder, 1, 1);
+        display.set_pixel(Point::new(0, 0), Color::Red).unwrap();
+        display.present().unwrap();
+        // Only the top-left dot is on, drawn in red over the canvas.
+        assert_eq!(out.take(), "\x1b[1;1H\x1b[38;5;9m\x1b[48;5;12m⠁\x1b[1;1H");
+    }
+}